@@ -0,0 +1,166 @@
+use std::cmp;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::WhoisResult;
+use error::WhoisError;
+
+/// Look up each handle, recording a result for every one of them in the
+/// order of `handles`.
+///
+/// Up to `jobs` handles are looked up at once, each on its own thread, and
+/// with `jobs` of 1 or less they are looked up in turn on this one.
+///
+/// With `timeout_per_handle` set, a handle whose lookup (including any
+/// referrals it follows) takes longer than that is abandoned and recorded
+/// as `WhoisError::HandleTimeout`, and the batch moves on. The abandoned
+/// lookup finishes in the background and its result is discarded.
+pub fn lookup_all<F>(lookup: Arc<F>,
+                     handles: &[String],
+                     timeout_per_handle: Option<Duration>,
+                     jobs: usize)
+                     -> Vec<Result<WhoisResult, WhoisError>>
+    where F: Fn(&str) -> Result<WhoisResult, WhoisError> + Send + Sync + 'static
+{
+    if jobs <= 1 || handles.len() <= 1 {
+        return handles.iter().map(|handle| lookup_one(&lookup, handle, timeout_per_handle)).collect();
+    }
+
+    let handles = Arc::new(handles.to_vec());
+    // The index of the next handle for a thread to look up
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..cmp::min(jobs, handles.len()) {
+        let (lookup, handles, next, sender) = (lookup.clone(), handles.clone(), next.clone(), sender.clone());
+        thread::spawn(move || loop {
+            let i = next.fetch_add(1, Ordering::SeqCst);
+            let handle = match handles.get(i) {
+                Some(handle) => handle,
+                None => break,
+            };
+            let _ = sender.send((i, lookup_one(&lookup, handle, timeout_per_handle)));
+        });
+    }
+    drop(sender);
+
+    let mut results: Vec<Option<Result<WhoisResult, WhoisError>>> = handles.iter().map(|_| None).collect();
+    for (i, result) in receiver {
+        results[i] = Some(result);
+    }
+    results.into_iter()
+        .zip(handles.iter())
+        .map(|(result, handle)| result.unwrap_or_else(|| panic!("Lookup of {} panicked", handle)))
+        .collect()
+}
+
+fn lookup_one<F>(lookup: &Arc<F>, handle: &str, timeout_per_handle: Option<Duration>) -> Result<WhoisResult, WhoisError>
+    where F: Fn(&str) -> Result<WhoisResult, WhoisError> + Send + Sync + 'static
+{
+    match timeout_per_handle {
+        Some(timeout) => lookup_with_timeout(lookup.clone(), handle, timeout),
+        None => lookup(handle),
+    }
+}
+
+fn lookup_with_timeout<F>(lookup: Arc<F>, handle: &str, timeout: Duration) -> Result<WhoisResult, WhoisError>
+    where F: Fn(&str) -> Result<WhoisResult, WhoisError> + Send + Sync + 'static
+{
+    let (sender, receiver) = mpsc::channel();
+    let thread_handle = handle.to_string();
+    thread::spawn(move || {
+        // The receiver is gone if the lookup took too long
+        let _ = sender.send(lookup(&thread_handle));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(WhoisError::HandleTimeout(timeout)),
+        Err(RecvTimeoutError::Disconnected) => panic!("Lookup of {} panicked", handle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::lookup_all;
+    use super::super::{WhoisResult, WhoisIpResult};
+    use error::WhoisError;
+
+    fn lookup(handle: &str) -> Result<WhoisResult, WhoisError> {
+        if handle == "SLOW" {
+            thread::sleep(Duration::from_millis(500));
+        }
+        Ok(WhoisResult::new(Vec::new()))
+    }
+
+    #[test]
+    fn slow_handle_times_out() {
+        let handles = vec![String::from("FAST1"), String::from("SLOW"), String::from("FAST2")];
+        let results = lookup_all(Arc::new(lookup), &handles, Some(Duration::from_millis(100)), 1);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        match results[1] {
+            Err(WhoisError::HandleTimeout(timeout)) => assert_eq!(timeout, Duration::from_millis(100)),
+            ref other => panic!("Expected a handle timeout, got {:?}", other),
+        }
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn no_timeout() {
+        let handles = vec![String::from("SLOW"), String::from("FAST")];
+        let results = lookup_all(Arc::new(lookup), &handles, None, 1);
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn concurrent_lookups() {
+        // How many lookups are running, and the most there have been at once
+        let running = Arc::new(Mutex::new((0, 0)));
+        let lookup_running = running.clone();
+        let lookup = move |handle: &str| -> Result<WhoisResult, WhoisError> {
+            {
+                let mut running = lookup_running.lock().unwrap();
+                running.0 += 1;
+                running.1 = running.1.max(running.0);
+            }
+            thread::sleep(Duration::from_millis(50));
+            lookup_running.lock().unwrap().0 -= 1;
+            if handle == "FAILS" {
+                return Err(WhoisError::HandleTimeout(Duration::from_secs(0)));
+            }
+            let ip = IpAddr::from_str("192.0.2.0").unwrap();
+            Ok(WhoisResult::new(vec![WhoisIpResult::new(handle.to_string(), ip, ip)]))
+        };
+        let handles: Vec<String> = (0..8).map(|i| format!("ORG{}", i)).chain(Some(String::from("FAILS"))).collect();
+        let results = lookup_all(Arc::new(lookup), &handles, None, 3);
+
+        assert_eq!(results.len(), handles.len());
+        for (handle, result) in handles.iter().zip(&results) {
+            match *result {
+                Ok(ref result) => assert_eq!(&result.ips[0].name, handle),
+                Err(_) => assert_eq!(handle, "FAILS"),
+            }
+        }
+        assert!(results[8].is_err());
+        assert_eq!(running.lock().unwrap().1, 3);
+    }
+
+    #[test]
+    fn concurrent_lookups_time_out() {
+        let handles = vec![String::from("SLOW"), String::from("FAST1"), String::from("FAST2")];
+        let results = lookup_all(Arc::new(lookup), &handles, Some(Duration::from_millis(100)), 2);
+        match results[0] {
+            Err(WhoisError::HandleTimeout(_)) => {}
+            ref other => panic!("Expected a handle timeout, got {:?}", other),
+        }
+        assert!(results[1].is_ok() && results[2].is_ok());
+    }
+}
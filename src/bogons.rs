@@ -0,0 +1,69 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use cidr::{self, IpNet};
+
+/// Prefixes that should never be routed on the public internet: private,
+/// loopback, link-local, shared, documentation, benchmarking, multicast and
+/// reserved space (RFC 6890 and friends).
+pub const BOGONS: [&str; 23] = ["0.0.0.0/8",
+                                "10.0.0.0/8",
+                                "100.64.0.0/10",
+                                "127.0.0.0/8",
+                                "169.254.0.0/16",
+                                "172.16.0.0/12",
+                                "192.0.0.0/24",
+                                "192.0.2.0/24",
+                                "192.168.0.0/16",
+                                "198.18.0.0/15",
+                                "198.51.100.0/24",
+                                "203.0.113.0/24",
+                                "224.0.0.0/4",
+                                "240.0.0.0/4",
+                                "::/128",
+                                "::1/128",
+                                "::ffff:0:0/96",
+                                "100::/64",
+                                "2001:db8::/32",
+                                "fc00::/7",
+                                "fe80::/10",
+                                "fec0::/10",
+                                "ff00::/8"];
+
+fn bogon_ranges() -> Vec<(IpAddr, IpAddr)> {
+    BOGONS.iter()
+        .map(|bogon| IpNet::from_str(bogon).unwrap())
+        .map(|net| (net.addr(), net.last_addr()))
+        .collect()
+}
+
+/// `nets` with every bogon address removed, splitting prefixes that are
+/// only partly bogon into the fewest prefixes covering the rest.
+pub fn exclude(nets: &[IpNet]) -> Vec<IpNet> {
+    let bogons = bogon_ranges();
+    nets.iter().flat_map(|net| cidr::complement(*net, &bogons)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{BOGONS, bogon_ranges, exclude};
+    use cidr::IpNet;
+
+    fn excluded(nets: &[&str]) -> Vec<String> {
+        let nets: Vec<IpNet> = nets.iter().map(|net| IpNet::from_str(net).unwrap()).collect();
+        exclude(&nets).iter().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn bogons_parse() {
+        assert_eq!(bogon_ranges().len(), BOGONS.len());
+    }
+
+    #[test]
+    fn exclude_bogons() {
+        assert_eq!(excluded(&["162.125.0.0/16", "10.0.0.0/8", "2001:db8::/48"]), vec!["162.125.0.0/16"]);
+        assert_eq!(excluded(&["8.0.0.0/6"]), vec!["8.0.0.0/7", "11.0.0.0/8"]);
+    }
+}
@@ -0,0 +1,140 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use super::{WhoisResult, WhoisIpResult};
+use xmlparser::ParseError;
+
+/// Parses the net records of ARIN's bulk Whois flat file, for users with a
+/// bulk access agreement, keeping only the nets of one org.
+///
+/// Records are blocks of `Key: Value` lines separated by blank lines, and
+/// only those with a `NetHandle` are nets:
+///
+/// ```text
+/// NetHandle:      NET-162-125-0-0-1
+/// OrgID:          DROPB
+/// NetName:        DROPB
+/// NetRange:       162.125.0.0 - 162.125.255.255
+/// ```
+///
+/// Lines starting with `#` are comments.
+#[derive(Debug)]
+pub struct BulkFileParser {
+    org: String,
+}
+
+impl BulkFileParser {
+    /// A parser keeping the nets whose `OrgID` is `org`, ignoring case.
+    pub fn new(org: &str) -> BulkFileParser {
+        BulkFileParser { org: org.to_string() }
+    }
+
+    pub fn parse(&self, content: &str) -> Result<WhoisResult, ParseError> {
+        let mut ips = Vec::new();
+        let mut record: Vec<(&str, &str)> = Vec::new();
+        for line in content.lines().chain(Some("")) {
+            let line = line.trim_end();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.trim().is_empty() {
+                if let Some(ip) = self.parse_record(&record)? {
+                    ips.push(ip);
+                }
+                record.clear();
+                continue;
+            }
+            match line.find(':') {
+                Some(i) => record.push((line[..i].trim(), line[i + 1..].trim())),
+                None => return Err(ParseError::BulkRecord(format!("Expected a Key: Value line: {}", line))),
+            }
+        }
+        Ok(WhoisResult::new(ips))
+    }
+
+    /// The net a record describes if it is one of the org's nets.
+    fn parse_record(&self, record: &[(&str, &str)]) -> Result<Option<WhoisIpResult>, ParseError> {
+        let value = |key: &str| record.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v);
+        let handle = match value("NetHandle") {
+            Some(handle) => handle,
+            None => return Ok(None),
+        };
+        match value("OrgID") {
+            Some(org) if org.eq_ignore_ascii_case(&self.org) => {}
+            _ => return Ok(None),
+        }
+        let range = value("NetRange")
+            .ok_or_else(|| ParseError::BulkRecord(format!("Net {} has no NetRange", handle)))?;
+        let mut bounds = range.splitn(2, '-').map(|addr| addr.trim());
+        let (start_ip, end_ip) = match (bounds.next(), bounds.next()) {
+            (Some(start), Some(end)) => (parse_ip(start)?, parse_ip(end)?),
+            _ => return Err(ParseError::BulkRecord(format!("Net {} has an invalid NetRange: {}", handle, range))),
+        };
+        let name = value("NetName").unwrap_or(handle);
+        let mut ip = WhoisIpResult::new(name.to_string(), start_ip, end_ip);
+        ip.handle = Some(handle.to_string());
+        ip.parent = value("Parent").filter(|parent| !parent.is_empty()).map(String::from);
+        Ok(Some(ip))
+    }
+}
+
+fn parse_ip(ip_str: &str) -> Result<IpAddr, ParseError> {
+    IpAddr::from_str(ip_str)
+        .map_err(|e| ParseError::IpAddrError(format!("Failed to parse IP address: {:} ({:})", e, ip_str)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BulkFileParser;
+    use xmlparser::ParseError;
+
+    const SAMPLE: &str = "# ARIN bulk Whois sample
+OrgID:          DROPB
+OrgName:        Dropbox, Inc.
+
+NetHandle:      NET-162-125-0-0-1
+OrgID:          DROPB
+Parent:         NET-162-0-0-0-0
+NetName:        DROPB
+NetRange:       162.125.0.0 - 162.125.255.255
+
+NetHandle:      NET-8-8-8-0-1
+OrgID:          GOGL
+NetName:        LVLT-GOGL-8-8-8
+NetRange:       8.8.8.0 - 8.8.8.255
+
+NetHandle:      NET6-2620-100-6000-1
+OrgID:          dropb
+NetName:        DROPBOX-V6
+NetRange:       2620:100:6000:: - 2620:100:6000:ffff:ffff:ffff:ffff:ffff
+";
+
+    #[test]
+    fn parse_filters_by_org() {
+        let result = BulkFileParser::new("DROPB").parse(SAMPLE).unwrap();
+        let nets: Vec<(&str, String, String)> = result.ips
+            .iter()
+            .map(|ip| (ip.name.as_ref(), ip.start_ip.to_string(), ip.end_ip.to_string()))
+            .collect();
+        assert_eq!(nets,
+                   vec![("DROPB", String::from("162.125.0.0"), String::from("162.125.255.255")),
+                        ("DROPBOX-V6",
+                         String::from("2620:100:6000::"),
+                         String::from("2620:100:6000:ffff:ffff:ffff:ffff:ffff"))]);
+        assert_eq!(result.ips[0].handle, Some(String::from("NET-162-125-0-0-1")));
+        assert_eq!(result.ips[0].parent, Some(String::from("NET-162-0-0-0-0")));
+
+        assert!(BulkFileParser::new("NOSUCHORG").parse(SAMPLE).unwrap().ips.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_range() {
+        let sample = "NetHandle: NET-10-0-0-0-1\nOrgID: DROPB\nNetRange: 10.0.0.0\n";
+        match BulkFileParser::new("DROPB").parse(sample) {
+            Err(ParseError::BulkRecord(_)) => {}
+            other => panic!("Expected an invalid record, got {:?}", other),
+        }
+        let sample = "NetHandle: NET-10-0-0-0-1\nOrgID: DROPB\nNetRange: 10.0.0.0 - 10.0.0.x\n";
+        assert!(BulkFileParser::new("DROPB").parse(sample).is_err());
+    }
+}
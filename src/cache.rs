@@ -0,0 +1,174 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use hyper::status::StatusCode;
+use sha2::{Digest, Sha256};
+
+use httpclient::{WhoisHttpClient, HttpClientError, FetchMeta};
+
+/// Wraps a `WhoisHttpClient`, keeping the body of every successful GET in a
+/// file under `cache_dir` and answering from it for `ttl` afterwards.
+///
+/// Responses are keyed on the URL alone, so a cache directory should only be
+/// shared by clients asking for the same media type. A cache that cannot be
+/// read or written is bypassed rather than failing the request.
+#[derive(Debug)]
+pub struct CachingWhoisHttpClient<C: WhoisHttpClient> {
+    client: C,
+    cache_dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+impl<C: WhoisHttpClient> CachingWhoisHttpClient<C> {
+    /// With `cache_dir` of `None` every request goes to `client`.
+    pub fn new(client: C, cache_dir: Option<PathBuf>, ttl: Duration) -> CachingWhoisHttpClient<C> {
+        CachingWhoisHttpClient {
+            client: client,
+            cache_dir: cache_dir,
+            ttl: ttl,
+        }
+    }
+
+    /// The file the response for `url` is kept in, named by the URL's hex
+    /// SHA-256.
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        let mut hasher = Sha256::new();
+        hasher.input(url);
+        self.cache_dir.as_ref().map(|dir| dir.join(format!("{:x}", hasher.result())))
+    }
+
+    /// The body cached at `path`, if it was written less than `ttl` ago.
+    fn read_cached(&self, path: &Path) -> Option<String> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if age >= self.ttl {
+            return None;
+        }
+        let mut body = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut body)).ok()?;
+        Some(body)
+    }
+
+    fn write_cached(&self, path: &Path, body: &str) {
+        let written = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| File::create(path))
+            .and_then(|mut file| file.write_all(body.as_bytes()));
+        if let Err(err) = written {
+            warn!("Failed to cache {}: {}", path.display(), err);
+        }
+    }
+}
+
+impl<C: WhoisHttpClient> WhoisHttpClient for CachingWhoisHttpClient<C> {
+    fn get_content(&self, url: &str) -> Result<String, HttpClientError> {
+        self.fetch(url).map(|(body, _)| body)
+    }
+
+    fn fetch(&self, url: &str) -> Result<(String, FetchMeta), HttpClientError> {
+        let path = match self.cache_path(url) {
+            Some(path) => path,
+            None => return self.client.fetch(url),
+        };
+        if let Some(body) = self.read_cached(&path) {
+            debug!("Cache hit for {}", url);
+            let meta = FetchMeta {
+                url: url.to_string(),
+                status: StatusCode::Ok,
+                retry_count: 0,
+            };
+            return Ok((body, meta));
+        }
+        let (body, meta) = self.client.fetch(url)?;
+        self.write_cached(&path, &body);
+        Ok((body, meta))
+    }
+
+    // A POST's response depends on its body as well, so it is never cached
+    fn post_content(&self, url: &str, body: &str, content_type: &str) -> Result<String, HttpClientError> {
+        self.client.post_content(url, body, content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::CachingWhoisHttpClient;
+    use httpclient::{WhoisHttpClient, HttpClientError};
+
+    /// Answers every request with the number of requests made so far.
+    struct CountingHttpClient {
+        calls: Cell<u32>,
+    }
+
+    impl WhoisHttpClient for CountingHttpClient {
+        fn get_content(&self, _url: &str) -> Result<String, HttpClientError> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(self.calls.get().to_string())
+        }
+    }
+
+    /// A fresh cache directory for the test `name`, removing any left over
+    /// from an earlier run.
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("whois-ips-cache-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn caching(dir: &Path, ttl: Duration) -> CachingWhoisHttpClient<CountingHttpClient> {
+        CachingWhoisHttpClient::new(CountingHttpClient { calls: Cell::new(0) }, Some(dir.to_path_buf()), ttl)
+    }
+
+    #[test]
+    fn cache_hit() {
+        let dir = cache_dir("hit");
+        let client = caching(&dir, Duration::from_secs(3600));
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.client.calls.get(), 1);
+
+        // Another client sharing the directory is answered from it as well
+        let client = caching(&dir, Duration::from_secs(3600));
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.client.calls.get(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_miss() {
+        let dir = cache_dir("miss");
+        let client = caching(&dir, Duration::from_secs(3600));
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/EXAMPLE/nets").unwrap(), "2");
+        assert_eq!(client.client.calls.get(), 2);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_expiry() {
+        let dir = cache_dir("expiry");
+        let client = caching(&dir, Duration::from_secs(0));
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "2");
+        assert_eq!(client.client.calls.get(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_cache_dir() {
+        let client = CachingWhoisHttpClient::new(CountingHttpClient { calls: Cell::new(0) },
+                                                 None,
+                                                 Duration::from_secs(3600));
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "1");
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "2");
+    }
+}
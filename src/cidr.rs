@@ -0,0 +1,388 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// A CIDR prefix such as `162.125.0.0/16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// The prefix `addr/prefix_len`, or `None` if `prefix_len` is too long
+    /// for the address family or `addr` has bits set past the prefix.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<IpNet> {
+        let bits = family_bits(addr);
+        if prefix_len > bits || to_u128(addr) & host_mask(bits, prefix_len) != 0 {
+            return None;
+        }
+        Some(IpNet {
+            addr: addr,
+            prefix_len: prefix_len,
+        })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The last address covered by this prefix.
+    pub fn last_addr(&self) -> IpAddr {
+        let bits = family_bits(self.addr);
+        from_u128(to_u128(self.addr) | host_mask(bits, self.prefix_len), self.addr.is_ipv4())
+    }
+
+    /// Where the inclusive range `start..=end` lies in this prefix, as the
+    /// offset of `start` from the prefix's first address and the number of
+    /// addresses, or `None` unless the whole range is inside the prefix.
+    ///
+    /// The length saturates at `u128::MAX` for the whole of `::/0`.
+    pub fn offset_of(&self, start: IpAddr, end: IpAddr) -> Option<(u128, u128)> {
+        let is_ipv4 = self.addr.is_ipv4();
+        if start.is_ipv4() != is_ipv4 || end.is_ipv4() != is_ipv4 || start > end {
+            return None;
+        }
+        let (first, last) = (to_u128(self.addr), to_u128(self.last_addr()));
+        let (start, end) = (to_u128(start), to_u128(end));
+        if start < first || end > last {
+            return None;
+        }
+        Some((start - first, (end - start).saturating_add(1)))
+    }
+
+    /// The inverse of the netmask as used by Cisco ACLs, e.g. `0.0.255.255`
+    /// for a /16.
+    pub fn wildcard_mask(&self) -> IpAddr {
+        from_u128(host_mask(family_bits(self.addr), self.prefix_len), self.addr.is_ipv4())
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IpNet, String> {
+        let mut parts = s.splitn(2, '/');
+        let addr = parts.next().and_then(|addr| IpAddr::from_str(addr).ok());
+        let prefix_len = parts.next().and_then(|len| len.parse::<u8>().ok());
+        match (addr, prefix_len) {
+            (Some(addr), Some(prefix_len)) => {
+                IpNet::new(addr, prefix_len).ok_or_else(|| format!("Invalid CIDR prefix: {}", s))
+            }
+            _ => Err(format!("Invalid CIDR prefix: {}", s)),
+        }
+    }
+}
+
+/// Iterator over the fewest CIDR prefixes that exactly cover a range.
+#[derive(Debug, Clone)]
+pub struct CidrIter {
+    next: Option<u128>,
+    end: u128,
+    is_ipv4: bool,
+}
+
+/// The fewest CIDR prefixes, in address order, that cover exactly the
+/// addresses from `start` to `end` inclusive.
+///
+/// `192.0.2.1`-`192.0.2.10` becomes `192.0.2.1/32`, `192.0.2.2/31`,
+/// `192.0.2.4/30`, `192.0.2.8/31` and `192.0.2.10/32`. A range whose ends
+/// are of different families, or whose end is before its start, yields
+/// nothing.
+pub fn decompose(start: IpAddr, end: IpAddr) -> CidrIter {
+    let next = if start.is_ipv4() == end.is_ipv4() && start <= end {
+        Some(to_u128(start))
+    } else {
+        None
+    };
+    CidrIter {
+        next: next,
+        end: to_u128(end),
+        is_ipv4: start.is_ipv4(),
+    }
+}
+
+/// Combine prefixes into the fewest prefixes covering the same addresses.
+///
+/// Overlapping and adjacent prefixes of each family are merged, so two
+/// aligned adjacent /24s become one /23, while two adjacent /24s that do not
+/// share a /23 stay as they are. IPv4 prefixes are returned before IPv6.
+pub fn aggregate(nets: &[IpNet]) -> Vec<IpNet> {
+    let mut intervals: Vec<(bool, u128, u128)> = nets.iter()
+        .map(|net| (!net.addr.is_ipv4(), to_u128(net.addr), to_u128(net.last_addr())))
+        .collect();
+    intervals.sort();
+
+    let mut merged: Vec<(bool, u128, u128)> = Vec::new();
+    for (is_ipv6, start, end) in intervals {
+        if let Some(last) = merged.last_mut() {
+            let touches = last.2 == u128::MAX || start <= last.2 + 1;
+            if last.0 == is_ipv6 && touches {
+                last.2 = last.2.max(end);
+                continue;
+            }
+        }
+        merged.push((is_ipv6, start, end));
+    }
+
+    merged.into_iter()
+        .flat_map(|(is_ipv6, start, end)| decompose(from_u128(start, !is_ipv6), from_u128(end, !is_ipv6)))
+        .collect()
+}
+
+/// The fewest prefixes covering every address in `supernet` that is not in
+/// any of the inclusive `ranges`.
+///
+/// Ranges of the other family, or that are reversed, cover nothing.
+pub fn complement(supernet: IpNet, ranges: &[(IpAddr, IpAddr)]) -> Vec<IpNet> {
+    let is_ipv4 = supernet.addr.is_ipv4();
+    let (first, last) = (to_u128(supernet.addr), to_u128(supernet.last_addr()));
+    let mut covered: Vec<(u128, u128)> = ranges.iter()
+        .filter(|&&(start, end)| start.is_ipv4() == is_ipv4 && end.is_ipv4() == is_ipv4 && start <= end)
+        .map(|&(start, end)| (to_u128(start).max(first), to_u128(end).min(last)))
+        .filter(|&(start, end)| start <= end)
+        .collect();
+    covered.sort();
+
+    let mut gaps = Vec::new();
+    // The first address not known to be covered, `None` once past `last`
+    let mut next = Some(first);
+    for (start, end) in covered {
+        let from = match next {
+            Some(from) => from,
+            None => break,
+        };
+        if start > from {
+            gaps.extend(decompose(from_u128(from, is_ipv4), from_u128(start - 1, is_ipv4)));
+        }
+        if end >= from {
+            next = if end == last { None } else { Some(end + 1) };
+        }
+    }
+    if let Some(from) = next {
+        gaps.extend(decompose(from_u128(from, is_ipv4), from_u128(last, is_ipv4)));
+    }
+    gaps
+}
+
+impl Iterator for CidrIter {
+    type Item = IpNet;
+
+    fn next(&mut self) -> Option<IpNet> {
+        let start = match self.next {
+            Some(start) => start,
+            None => return None,
+        };
+        let bits: u8 = if self.is_ipv4 { 32 } else { 128 };
+
+        // The largest block starting at `start` is limited both by the
+        // alignment of `start` and by how many addresses are left.
+        let aligned = if start == 0 { bits as u32 } else { start.trailing_zeros().min(bits as u32) };
+        let remaining = self.end - start;
+        let fits = if remaining == u128::MAX { 128 } else { 127 - (remaining + 1).leading_zeros() };
+        let host_bits = aligned.min(fits);
+
+        self.next = if host_bits >= 128 {
+            None
+        } else {
+            match start.checked_add(1u128 << host_bits) {
+                Some(next) if next <= self.end => Some(next),
+                _ => None,
+            }
+        };
+
+        let addr = from_u128(start, self.is_ipv4);
+        Some(IpNet {
+            addr: addr,
+            prefix_len: bits - host_bits as u8,
+        })
+    }
+}
+
+fn family_bits(addr: IpAddr) -> u8 {
+    if addr.is_ipv4() { 32 } else { 128 }
+}
+
+fn host_mask(bits: u8, prefix_len: u8) -> u128 {
+    let host_bits = (bits - prefix_len) as u32;
+    if host_bits >= 128 {
+        u128::MAX
+    } else {
+        (1u128 << host_bits) - 1
+    }
+}
+
+fn to_u128(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u32::from(addr) as u128,
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+fn from_u128(value: u128, is_ipv4: bool) -> IpAddr {
+    if is_ipv4 {
+        IpAddr::V4(Ipv4Addr::from(value as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use super::{IpNet, decompose, aggregate, complement};
+
+    fn net(s: &str) -> IpNet {
+        IpNet::from_str(s).unwrap()
+    }
+
+    fn complemented(supernet: &str, ranges: &[(&str, &str)]) -> Vec<String> {
+        let ranges: Vec<(IpAddr, IpAddr)> = ranges.iter()
+            .map(|&(start, end)| (IpAddr::from_str(start).unwrap(), IpAddr::from_str(end).unwrap()))
+            .collect();
+        complement(net(supernet), &ranges).iter().map(|net| net.to_string()).collect()
+    }
+
+    fn aggregated(nets: &[&str]) -> Vec<String> {
+        let nets: Vec<IpNet> = nets.iter().map(|s| net(s)).collect();
+        aggregate(&nets).iter().map(|net| net.to_string()).collect()
+    }
+
+    fn cidrs(start: &str, end: &str) -> Vec<String> {
+        decompose(IpAddr::from_str(start).unwrap(), IpAddr::from_str(end).unwrap())
+            .map(|net| net.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn decompose_single_block() {
+        assert_eq!(cidrs("162.125.0.0", "162.125.255.255"), vec!["162.125.0.0/16"]);
+        assert_eq!(cidrs("8.8.8.8", "8.8.8.8"), vec!["8.8.8.8/32"]);
+    }
+
+    #[test]
+    fn decompose_unaligned() {
+        assert_eq!(cidrs("192.0.2.1", "192.0.2.10"),
+                   vec!["192.0.2.1/32", "192.0.2.2/31", "192.0.2.4/30", "192.0.2.8/31", "192.0.2.10/32"]);
+    }
+
+    #[test]
+    fn decompose_whole_space() {
+        assert_eq!(cidrs("0.0.0.0", "255.255.255.255"), vec!["0.0.0.0/0"]);
+        assert_eq!(cidrs("::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), vec!["::/0"]);
+    }
+
+    #[test]
+    fn decompose_ipv6() {
+        assert_eq!(cidrs("2620:100::", "2620:100:ffff:ffff:ffff:ffff:ffff:ffff"),
+                   vec!["2620:100::/32"]);
+        assert_eq!(cidrs("2001:db8::1", "2001:db8::4"),
+                   vec!["2001:db8::1/128", "2001:db8::2/127", "2001:db8::4/128"]);
+    }
+
+    #[test]
+    fn offset_of() {
+        let net = IpNet::from_str("10.0.0.0/8").unwrap();
+        let offset = |start: &str, end: &str| {
+            net.offset_of(IpAddr::from_str(start).unwrap(), IpAddr::from_str(end).unwrap())
+        };
+        assert_eq!(offset("10.0.0.0", "10.255.255.255"), Some((0, 16777216)));
+        assert_eq!(offset("10.1.0.0", "10.1.255.255"), Some((65536, 65536)));
+        assert_eq!(offset("10.255.255.255", "10.255.255.255"), Some((16777215, 1)));
+        assert_eq!(offset("9.255.255.255", "10.0.0.255"), None);
+        assert_eq!(offset("10.255.255.0", "11.0.0.0"), None);
+        assert_eq!(offset("2001:db8::", "2001:db8::ff"), None);
+
+        let all = IpNet::from_str("::/0").unwrap();
+        assert_eq!(all.offset_of(IpAddr::from_str("::").unwrap(),
+                                 IpAddr::from_str("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff").unwrap()),
+                   Some((0, u128::MAX)));
+    }
+
+    #[test]
+    fn wildcard_mask() {
+        let mask = |net: &str| IpNet::from_str(net).unwrap().wildcard_mask().to_string();
+        assert_eq!(mask("162.125.0.0/16"), "0.0.255.255");
+        assert_eq!(mask("192.0.2.0/25"), "0.0.0.127");
+        assert_eq!(mask("8.8.8.8/32"), "0.0.0.0");
+        assert_eq!(mask("0.0.0.0/0"), "255.255.255.255");
+        assert_eq!(mask("2001:db8::/32"), "::ffff:ffff:ffff:ffff:ffff:ffff");
+    }
+
+    #[test]
+    fn decompose_invalid() {
+        assert!(cidrs("10.0.0.1", "10.0.0.0").is_empty());
+        assert!(cidrs("10.0.0.0", "2001:db8::").is_empty());
+    }
+
+    #[test]
+    fn ip_net_new() {
+        let addr = IpAddr::from_str("10.0.0.0").unwrap();
+        assert_eq!(IpNet::new(addr, 8).unwrap().to_string(), "10.0.0.0/8");
+        assert!(IpNet::new(addr, 33).is_none());
+        assert!(IpNet::new(IpAddr::from_str("10.0.0.1").unwrap(), 8).is_none());
+    }
+
+    #[test]
+    fn ip_net_from_str() {
+        assert_eq!(IpNet::from_str("2001:db8::/32").unwrap().prefix_len(), 32);
+        assert!(IpNet::from_str("10.0.0.1/8").is_err());
+        assert!(IpNet::from_str("10.0.0.0").is_err());
+        assert!(IpNet::from_str("example/8").is_err());
+    }
+
+    #[test]
+    fn ip_net_last_addr() {
+        assert_eq!(net("10.0.0.0/8").last_addr(), IpAddr::from_str("10.255.255.255").unwrap());
+        assert_eq!(net("8.8.8.8/32").last_addr(), IpAddr::from_str("8.8.8.8").unwrap());
+        assert_eq!(net("::/0").last_addr(),
+                   IpAddr::from_str("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff").unwrap());
+    }
+
+    #[test]
+    fn aggregate_aligned_neighbours() {
+        assert_eq!(aggregated(&["10.0.0.0/24", "10.0.1.0/24"]), vec!["10.0.0.0/23"]);
+        assert_eq!(aggregated(&["10.0.3.0/24", "10.0.0.0/23", "10.0.2.0/24"]), vec!["10.0.0.0/22"]);
+    }
+
+    #[test]
+    fn aggregate_misaligned_neighbours() {
+        assert_eq!(aggregated(&["10.0.1.0/24", "10.0.2.0/24"]), vec!["10.0.1.0/24", "10.0.2.0/24"]);
+    }
+
+    #[test]
+    fn aggregate_overlap_and_families() {
+        assert_eq!(aggregated(&["2001:db8::/33", "10.0.0.0/16", "10.0.5.0/24", "2001:db8:8000::/33"]),
+                   vec!["10.0.0.0/16", "2001:db8::/32"]);
+        assert_eq!(aggregated(&["255.255.255.255/32", "::/128"]), vec!["255.255.255.255/32", "::/128"]);
+        assert!(aggregated(&[]).is_empty());
+    }
+
+    #[test]
+    fn complement_fully_covered() {
+        assert!(complemented("10.0.0.0/23", &[("10.0.0.0", "10.0.0.255"), ("10.0.1.0", "10.0.1.255")]).is_empty());
+        assert!(complemented("10.0.0.0/24", &[("9.0.0.0", "11.0.0.0")]).is_empty());
+        assert!(complemented("::/0", &[("::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")]).is_empty());
+    }
+
+    #[test]
+    fn complement_with_hole() {
+        assert_eq!(complemented("10.0.0.0/22", &[("10.0.0.0", "10.0.0.255"), ("10.0.3.0", "10.0.3.255")]),
+                   vec!["10.0.1.0/24", "10.0.2.0/24"]);
+        assert_eq!(complemented("10.0.0.0/24", &[("10.0.0.1", "10.0.0.254")]),
+                   vec!["10.0.0.0/32", "10.0.0.255/32"]);
+        assert_eq!(complemented("10.0.0.0/24", &[("2001:db8::", "2001:db8::ff")]), vec!["10.0.0.0/24"]);
+    }
+}
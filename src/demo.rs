@@ -0,0 +1,76 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{WhoisResult, WhoisIpResult};
+
+/// The IPv4 documentation networks (RFC 5737), so demo output can never be
+/// mistaken for a real allocation.
+const DEMO_V4_NETS: [[u8; 3]; 3] = [[192, 0, 2], [198, 51, 100], [203, 0, 113]];
+
+/// SplitMix64, enough to vary the demo ranges with `--seed` while keeping
+/// each seed's output the same on every run and platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// A made-up result for `--demo`: one range in each IPv4 documentation
+/// network and one in `2001:db8::/32`, all named `DEMO-FAKE-<n>`.
+pub fn demo_result(seed: u64) -> WhoisResult {
+    let mut rng = SplitMix64(seed);
+    let mut ips = Vec::new();
+    for net in &DEMO_V4_NETS {
+        let size = 16u16 << (rng.next() % 3);
+        let start = (rng.next() % (256 / u64::from(size))) as u16 * size;
+        let end = start + size - 1;
+        ips.push((IpAddr::V4(Ipv4Addr::new(net[0], net[1], net[2], start as u8)),
+                  IpAddr::V4(Ipv4Addr::new(net[0], net[1], net[2], end as u8))));
+    }
+    let block = rng.next() as u16;
+    ips.push((IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, block, 0, 0, 0, 0, 0)),
+              IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, block, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff))));
+
+    let ips = ips.into_iter()
+        .enumerate()
+        .map(|(i, (start_ip, end_ip))| WhoisIpResult::new(format!("DEMO-FAKE-{}", i + 1), start_ip, end_ip))
+        .collect();
+    WhoisResult::new(ips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demo_result;
+    use output::{self, OutputFormat, OutputOptions};
+
+    fn json(seed: u64) -> String {
+        output::to_ranges_json(&demo_result(seed), &[]).unwrap()
+    }
+
+    #[test]
+    fn demo_result_stable() {
+        assert_eq!(json(0),
+                   "[{\"name\":\"DEMO-FAKE-1\",\"start_ip\":\"192.0.2.128\",\"end_ip\":\"192.0.2.159\"},\
+                    {\"name\":\"DEMO-FAKE-2\",\"start_ip\":\"198.51.100.128\",\"end_ip\":\"198.51.100.159\"},\
+                    {\"name\":\"DEMO-FAKE-3\",\"start_ip\":\"203.0.113.64\",\"end_ip\":\"203.0.113.95\"},\
+                    {\"name\":\"DEMO-FAKE-4\",\"start_ip\":\"2001:db8:32e1::\",\
+                    \"end_ip\":\"2001:db8:32e1:ffff:ffff:ffff:ffff:ffff\"}]");
+        assert_eq!(json(0), json(0));
+        assert!(json(0) != json(1));
+    }
+
+    #[test]
+    fn demo_result_all_formats() {
+        let result = demo_result(0);
+        for format in &[OutputFormat::Plain, OutputFormat::Json, OutputFormat::Summary] {
+            let mut out = Vec::new();
+            output::write_result(&result, *format, &OutputOptions::default(), &mut out).unwrap();
+            assert!(!out.is_empty());
+        }
+    }
+}
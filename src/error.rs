@@ -0,0 +1,155 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use httpclient::HttpClientError;
+use xmlparser::ParseError;
+
+use hyper::status::StatusCode;
+
+#[derive(Debug)]
+pub enum WhoisError {
+    Http(HttpClientError),
+    Parse(ParseError),
+    /// The registry referred the lookup to one this build cannot query
+    UnsupportedReferral(String),
+    /// Referrals went too deep or looped back to a registry already asked
+    TooManyReferrals(String),
+    /// A handle's lookup as a whole took longer than allowed
+    HandleTimeout(Duration),
+    /// A URL given to look up is not one we are willing to fetch
+    InvalidUrl(String),
+    /// The result could not be written out
+    Output(io::Error),
+}
+
+/// Broad category of a `WhoisError`, stable enough for scripts to match on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhoisErrorKind {
+    /// ARIN has no record for the query
+    NotFound,
+    /// ARIN is down for maintenance, or refusing requests sent too quickly
+    Unavailable,
+    /// Any other failure to get a response from ARIN
+    Http,
+    /// ARIN returned more results than it is willing to list
+    LimitExceeded,
+    /// The response could not be understood
+    Parse,
+    /// The lookup was referred to another registry and could not follow it
+    Referral,
+    /// The lookup took too long
+    Timeout,
+    /// The lookup was asked for something that cannot be looked up
+    InvalidInput,
+    /// Writing the result failed
+    Output,
+}
+
+impl WhoisError {
+    pub fn kind(&self) -> WhoisErrorKind {
+        match *self {
+            WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound)) => WhoisErrorKind::NotFound,
+            WhoisError::Http(HttpClientError::ServiceUnavailable { .. }) => WhoisErrorKind::Unavailable,
+            WhoisError::Http(HttpClientError::RateLimited { .. }) => WhoisErrorKind::Unavailable,
+            WhoisError::Http(HttpClientError::Timeout(_)) => WhoisErrorKind::Timeout,
+            WhoisError::Http(_) => WhoisErrorKind::Http,
+            WhoisError::Parse(ParseError::LimitExceeded { .. }) => WhoisErrorKind::LimitExceeded,
+            WhoisError::Parse(_) => WhoisErrorKind::Parse,
+            WhoisError::UnsupportedReferral(_) |
+            WhoisError::TooManyReferrals(_) => WhoisErrorKind::Referral,
+            WhoisError::HandleTimeout(_) => WhoisErrorKind::Timeout,
+            WhoisError::InvalidUrl(_) => WhoisErrorKind::InvalidInput,
+            WhoisError::Output(_) => WhoisErrorKind::Output,
+        }
+    }
+}
+
+impl fmt::Display for WhoisError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WhoisError::Http(ref err) => write!(fmt, "HTTP Error: {}", err),
+            WhoisError::Parse(ref err) => write!(fmt, "Parse Error: {}", err),
+            WhoisError::UnsupportedReferral(ref registry) => write!(fmt, "Referred to unsupported registry {}", registry),
+            WhoisError::TooManyReferrals(ref registry) => {
+                write!(fmt, "Too many referrals, last referred to {}", registry)
+            }
+            WhoisError::HandleTimeout(ref timeout) => {
+                write!(fmt, "Lookup took longer than {} seconds", timeout.as_secs())
+            }
+            WhoisError::InvalidUrl(ref url) => write!(fmt, "Refusing to fetch {}, only http and https URLs are allowed", url),
+            WhoisError::Output(ref err) => write!(fmt, "Failed to write the result: {}", err),
+        }
+    }
+}
+
+impl error::Error for WhoisError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            WhoisError::Http(ref err) => Some(err),
+            WhoisError::Parse(ref err) => Some(err),
+            WhoisError::Output(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpClientError> for WhoisError {
+    fn from(err: HttpClientError) -> WhoisError {
+        WhoisError::Http(err)
+    }
+}
+
+impl From<io::Error> for WhoisError {
+    fn from(err: io::Error) -> WhoisError {
+        WhoisError::Output(err)
+    }
+}
+
+impl From<ParseError> for WhoisError {
+    fn from(err: ParseError) -> WhoisError {
+        WhoisError::Parse(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use hyper::status::StatusCode;
+
+    use httpclient::HttpClientError;
+    use xmlparser::ParseError;
+
+    use super::{WhoisError, WhoisErrorKind};
+
+    #[test]
+    fn kind() {
+        let not_found = WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound));
+        assert_eq!(not_found.kind(), WhoisErrorKind::NotFound);
+        let server_error = WhoisError::Http(HttpClientError::HttpError(StatusCode::InternalServerError));
+        assert_eq!(server_error.kind(), WhoisErrorKind::Http);
+        let limit = WhoisError::from(ParseError::LimitExceeded { limit: None });
+        assert_eq!(limit.kind(), WhoisErrorKind::LimitExceeded);
+        let xml = WhoisError::from(ParseError::XmlError(String::from("bad")));
+        assert_eq!(xml.kind(), WhoisErrorKind::Parse);
+    }
+
+    #[test]
+    fn source() {
+        let err = WhoisError::from(ParseError::XmlError(String::from("bad")));
+        assert_eq!(err.source().unwrap().to_string(), "bad");
+        let err: Box<dyn Error> = Box::new(WhoisError::TooManyReferrals(String::from("RIPE")));
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn limit_exceeded_message() {
+        let limit = WhoisError::from(ParseError::LimitExceeded { limit: Some(String::from("256")) });
+        assert_eq!(limit.to_string(), "Parse Error: API result limit of 256 exceeded");
+        let limit = WhoisError::from(ParseError::LimitExceeded { limit: None });
+        assert_eq!(limit.to_string(), "Parse Error: API result limit exceeded");
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+
+use super::WhoisIpResult;
+
+/// Write the nets found for `org` as a tree for `--org-hierarchy`.
+///
+/// Each org is followed by its nets, then by the orgs those nets are
+/// reassigned to, indented one level further. A net without an `org` is
+/// taken to belong to the org of its parent net, or to `org` itself if it
+/// has none. `ips` must list parent nets before their children, as
+/// `include_children` does.
+pub fn write_tree<W: Write>(org: &str, ips: &[WhoisIpResult], w: &mut W) -> io::Result<()> {
+    let mut orgs: Vec<String> = Vec::with_capacity(ips.len());
+    for ip in ips {
+        let parent_org = ip.parent
+            .as_ref()
+            .and_then(|parent| ips.iter().position(|other| other.handle.as_ref() == Some(parent)))
+            .and_then(|i| orgs.get(i).cloned());
+        let ip_org = ip.org.clone().or(parent_org).unwrap_or_else(|| org.to_string());
+        orgs.push(ip_org);
+    }
+    let mut written = vec![org.to_string()];
+    write_org(org, 0, ips, &orgs, &mut written, w)
+}
+
+fn write_org<W: Write>(org: &str,
+                       depth: usize,
+                       ips: &[WhoisIpResult],
+                       orgs: &[String],
+                       written: &mut Vec<String>,
+                       w: &mut W)
+                       -> io::Result<()> {
+    writeln!(w, "{}{}", "  ".repeat(depth), org)?;
+    let nets: Vec<usize> = (0..ips.len()).filter(|&i| orgs[i] == org).collect();
+    for &i in &nets {
+        writeln!(w, "{}{} {} - {}", "  ".repeat(depth + 1), ips[i].name, ips[i].start_ip, ips[i].end_ip)?;
+    }
+
+    let mut child_orgs: Vec<&str> = Vec::new();
+    for (i, ip) in ips.iter().enumerate() {
+        let from_this_org = nets.iter().any(|&net| ips[net].handle.is_some() && ips[net].handle == ip.parent);
+        if from_this_org && orgs[i] != org && !child_orgs.contains(&orgs[i].as_ref()) {
+            child_orgs.push(&orgs[i]);
+        }
+    }
+    for child_org in child_orgs {
+        // An org reached through two parents is only written the first time
+        if written.iter().any(|org| org == child_org) {
+            continue;
+        }
+        written.push(child_org.to_string());
+        write_org(child_org, depth + 1, ips, orgs, written, w)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use super::write_tree;
+    use super::super::WhoisIpResult;
+
+    fn net(name: &str, start: &str, end: &str, handle: &str, parent: Option<&str>) -> WhoisIpResult {
+        let mut ip = WhoisIpResult::new(String::from(name),
+                                        IpAddr::from_str(start).unwrap(),
+                                        IpAddr::from_str(end).unwrap());
+        ip.handle = Some(String::from(handle));
+        ip.parent = parent.map(String::from);
+        ip
+    }
+
+    #[test]
+    fn write_tree_child_org() {
+        let mut customer = net("EXAMPLE-CUST",
+                               "162.125.0.0",
+                               "162.125.0.255",
+                               "NET-162-125-0-0-2",
+                               Some("NET-162-125-0-0-1"));
+        customer.org = Some(String::from("EXAMPLE"));
+        let ips = vec![net("DROPB", "162.125.0.0", "162.125.255.255", "NET-162-125-0-0-1", None), customer];
+
+        let mut out = Vec::new();
+        write_tree("DROPB", &ips, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "DROPB\n  DROPB 162.125.0.0 - 162.125.255.255\n  EXAMPLE\n    EXAMPLE-CUST 162.125.0.0 - \
+                    162.125.0.255\n");
+    }
+
+    #[test]
+    fn write_tree_same_org_child() {
+        let ips = vec![net("DROPB", "162.125.0.0", "162.125.255.255", "NET-162-125-0-0-1", None),
+                       net("DROPB-A",
+                           "162.125.0.0",
+                           "162.125.127.255",
+                           "NET-162-125-0-0-2",
+                           Some("NET-162-125-0-0-1"))];
+        let mut out = Vec::new();
+        write_tree("DROPB", &ips, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "DROPB\n  DROPB 162.125.0.0 - 162.125.255.255\n  DROPB-A 162.125.0.0 - 162.125.127.255\n");
+    }
+}
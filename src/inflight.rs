@@ -0,0 +1,209 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+use httpclient::{WhoisHttpClient, HttpClientError, FetchMeta};
+
+/// Wraps a `WhoisHttpClient`, letting at most `max_inflight` requests run
+/// at the same time however many threads share it.
+///
+/// Lookups abandoned by `--timeout-per-handle` keep running in the
+/// background, so without a cap the number of requests open against ARIN
+/// can grow with every slow handle.
+#[derive(Debug)]
+pub struct InflightLimitedWhoisHttpClient<C: WhoisHttpClient> {
+    client: C,
+    limit: Option<Arc<InflightLimit>>,
+}
+
+/// The permits behind an `InflightLimitedWhoisHttpClient`, which several
+/// clients can share to stay under one cap between them, e.g. the ARIN
+/// client and the ones its referrals are followed with.
+#[derive(Debug)]
+pub struct InflightLimit {
+    max_inflight: usize,
+    inflight: Mutex<usize>,
+    released: Condvar,
+}
+
+impl InflightLimit {
+    /// # Panics
+    ///
+    /// If `max_inflight` is 0, which would hold back every request forever.
+    pub fn new(max_inflight: usize) -> InflightLimit {
+        assert!(max_inflight != 0, "max_inflight must be at least 1");
+        InflightLimit {
+            max_inflight: max_inflight,
+            inflight: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire<'a>(&'a self) -> Permit<'a> {
+        let mut inflight = self.inflight.lock().unwrap();
+        while *inflight >= self.max_inflight {
+            inflight = self.released.wait(inflight).unwrap();
+        }
+        *inflight += 1;
+        Permit { limit: self }
+    }
+}
+
+/// Parse a `--max-inflight`, which has to let at least one request through.
+pub fn parse_max_inflight(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(String::from("--max-inflight must be at least 1")),
+        Ok(max_inflight) => Ok(max_inflight),
+        Err(e) => Err(format!("Invalid --max-inflight {:?}: {}", s, e)),
+    }
+}
+
+/// Gives back its permit when the request is done, however it ends.
+struct Permit<'a> {
+    limit: &'a InflightLimit,
+}
+
+impl<'a> Drop for Permit<'a> {
+    fn drop(&mut self) {
+        *self.limit.inflight.lock().unwrap() -= 1;
+        self.limit.released.notify_one();
+    }
+}
+
+impl<C: WhoisHttpClient> InflightLimitedWhoisHttpClient<C> {
+    /// With `max_inflight` of `None` requests are never held back.
+    ///
+    /// # Panics
+    ///
+    /// If `max_inflight` is `Some(0)`, which would hold back every request
+    /// forever.
+    pub fn new(client: C, max_inflight: Option<usize>) -> InflightLimitedWhoisHttpClient<C> {
+        InflightLimitedWhoisHttpClient::with_limit(client, max_inflight.map(|m| Arc::new(InflightLimit::new(m))))
+    }
+
+    /// Takes its permits from `limit`, counting its requests together with
+    /// those of every other client sharing it.
+    pub fn with_limit(client: C, limit: Option<Arc<InflightLimit>>) -> InflightLimitedWhoisHttpClient<C> {
+        InflightLimitedWhoisHttpClient {
+            client: client,
+            limit: limit,
+        }
+    }
+
+    fn acquire<'a>(&'a self) -> Option<Permit<'a>> {
+        self.limit.as_ref().map(|limit| limit.acquire())
+    }
+}
+
+impl<C: WhoisHttpClient> WhoisHttpClient for InflightLimitedWhoisHttpClient<C> {
+    fn get_content(&self, url: &str) -> Result<String, HttpClientError> {
+        self.fetch(url).map(|(body, _)| body)
+    }
+
+    fn fetch(&self, url: &str) -> Result<(String, FetchMeta), HttpClientError> {
+        let _permit = self.acquire();
+        self.client.fetch(url)
+    }
+
+    fn post_content(&self, url: &str, body: &str, content_type: &str) -> Result<String, HttpClientError> {
+        let _permit = self.acquire();
+        self.client.post_content(url, body, content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{InflightLimitedWhoisHttpClient, InflightLimit, parse_max_inflight};
+    use httpclient::{WhoisHttpClient, HttpClientError};
+
+    /// Takes a while to answer, recording the most requests it saw at once.
+    #[derive(Default)]
+    struct SlowHttpClient {
+        /// Requests running now, and the most seen at once
+        inflight: Mutex<(usize, usize)>,
+    }
+
+    impl WhoisHttpClient for SlowHttpClient {
+        fn get_content(&self, _url: &str) -> Result<String, HttpClientError> {
+            {
+                let mut inflight = self.inflight.lock().unwrap();
+                inflight.0 += 1;
+                inflight.1 = inflight.1.max(inflight.0);
+            }
+            thread::sleep(Duration::from_millis(20));
+            self.inflight.lock().unwrap().0 -= 1;
+            Ok(String::new())
+        }
+    }
+
+    /// Lets several clients send their requests to one `SlowHttpClient`.
+    impl WhoisHttpClient for Arc<SlowHttpClient> {
+        fn get_content(&self, url: &str) -> Result<String, HttpClientError> {
+            (**self).get_content(url)
+        }
+    }
+
+    fn max_inflight_seen(max_inflight: Option<usize>) -> usize {
+        let client = Arc::new(InflightLimitedWhoisHttpClient::new(SlowHttpClient::default(), max_inflight));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                thread::spawn(move || client.get_content("http://whois.arin.net/rest/org/DROPB").unwrap())
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let seen = client.client.inflight.lock().unwrap().1;
+        seen
+    }
+
+    #[test]
+    fn never_exceeds_cap() {
+        let seen = max_inflight_seen(Some(2));
+        assert!(seen >= 1 && seen <= 2, "saw {} requests at once", seen);
+        assert_eq!(max_inflight_seen(Some(1)), 1);
+    }
+
+    #[test]
+    fn shared_limit() {
+        // Both clients report to the same SlowHttpClient counts, so two
+        // requests at once would mean the cap was kept per client
+        let backend = Arc::new(SlowHttpClient::default());
+        let limit = Arc::new(InflightLimit::new(1));
+        let clients: Vec<_> = (0..2)
+            .map(|_| Arc::new(InflightLimitedWhoisHttpClient::with_limit(backend.clone(), Some(limit.clone()))))
+            .collect();
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let client = clients[i % 2].clone();
+                thread::spawn(move || client.get_content("http://whois.arin.net/rest/org/DROPB").unwrap())
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(backend.inflight.lock().unwrap().1, 1);
+    }
+
+    #[test]
+    fn unlimited() {
+        assert!(max_inflight_seen(None) >= 1);
+    }
+
+    #[test]
+    fn zero_max_inflight() {
+        assert_eq!(parse_max_inflight("4"), Ok(4));
+        assert!(parse_max_inflight("0").is_err());
+        assert!(parse_max_inflight("-1").is_err());
+        assert!(parse_max_inflight("many").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "max_inflight must be at least 1")]
+    fn zero_max_inflight_panics() {
+        InflightLimitedWhoisHttpClient::new(SlowHttpClient::default(), Some(0));
+    }
+}
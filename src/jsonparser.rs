@@ -0,0 +1,433 @@
+use std::io;
+
+use serde_json::{self, Value};
+
+use super::{WhoisResult, WhoisIpResult};
+use source::REFERRAL_HANDLES;
+use xmlparser::{WhoisXmlParser, StdWhoisXmlParser, NetDetails, ParseError, ParseWarning, PocLink};
+
+/// Parses the JSON ARIN sends for `Accept: application/json` into the same
+/// results as `StdWhoisXmlParser` does its XML.
+///
+/// ARIN's JSON follows its XML element for element. Attributes become `@`
+/// keys and text becomes `$`. An element that may repeat is an object when
+/// there is one and an array when there are more:
+///
+/// ```text
+/// {"nets": {"limitExceeded": {"@limit": "256", "$": "false"},
+///           "netRef": {"@startAddress": "162.125.0.0", "@endAddress": "162.125.255.255",
+///                      "@handle": "NET-162-125-0-0-1", "@name": "DROPB",
+///                      "$": "https://whois.arin.net/rest/net/NET-162-125-0-0-1"}}}
+/// ```
+#[derive(Debug)]
+pub struct JsonWhoisParser {
+    strict: bool,
+    allow_partial: bool,
+}
+
+impl JsonWhoisParser {
+    pub fn new() -> JsonWhoisParser {
+        JsonWhoisParser {
+            strict: false,
+            allow_partial: false,
+        }
+    }
+
+    /// In strict mode any `ParseWarning` fails the parse instead.
+    pub fn strict(mut self, strict: bool) -> JsonWhoisParser {
+        self.strict = strict;
+        self
+    }
+
+    /// Return the nets ARIN did send when it hits its result limit, with a
+    /// `ParseWarning::LimitExceeded`, instead of `ParseError::LimitExceeded`.
+    pub fn allow_partial(mut self, allow_partial: bool) -> JsonWhoisParser {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    fn warn(&self, warnings: &mut Vec<ParseWarning>, warning: ParseWarning) -> Result<(), ParseError> {
+        if self.strict {
+            return Err(ParseError::Strict(warning));
+        }
+        warnings.push(warning);
+        Ok(())
+    }
+
+    fn read<T: io::Read>(json: T) -> Result<Value, ParseError> {
+        serde_json::from_reader(json).map_err(|e| ParseError::JsonError(e.to_string()))
+    }
+
+    /// The `name` object the whole response is wrapped in.
+    fn root<'a>(value: &'a Value, name: &str) -> Result<&'a Value, ParseError> {
+        match value.get(name) {
+            Some(root) if root.is_object() => Ok(root),
+            _ => Err(ParseError::JsonError(format!("Expected a {} object", name))),
+        }
+    }
+
+    fn parse_netref(netref: &Value) -> Result<WhoisIpResult, ParseError> {
+        let required = |name: &str| {
+            attribute(netref, name).ok_or_else(|| ParseError::JsonError(format!("netRef has no {}", name)))
+        };
+        let start_ip = StdWhoisXmlParser::parse_ip(required("startAddress")?)?;
+        let end_ip = StdWhoisXmlParser::parse_ip(required("endAddress")?)?;
+        let mut ip_result = WhoisIpResult::new(required("name")?.to_string(), start_ip, end_ip);
+        ip_result.handle = attribute(netref, "handle").map(String::from);
+        Ok(ip_result)
+    }
+}
+
+/// Every element of `value`, whether ARIN sent one or several.
+fn elements(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(&Value::Array(ref values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    }
+}
+
+/// The text of an element, sent as `$` or as a bare string.
+fn text(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value.get("$").and_then(Value::as_str))
+}
+
+fn attribute<'a>(value: &'a Value, name: &str) -> Option<&'a str> {
+    value.get(&format!("@{}", name)).and_then(Value::as_str)
+}
+
+/// The last referral org found anywhere in `value`, as the XML parser does.
+fn find_referral(value: &Value) -> Option<String> {
+    let mut referral = None;
+    if let Some(object) = value.as_object() {
+        for (key, child) in object.iter() {
+            if key == "orgRef" {
+                for org in elements(Some(child)) {
+                    match attribute(org, "handle") {
+                        Some(handle) if REFERRAL_HANDLES.contains(&handle) => referral = Some(handle.to_string()),
+                        _ => {}
+                    }
+                }
+            }
+            referral = find_referral(child).or(referral);
+        }
+    } else if let Some(values) = value.as_array() {
+        for child in values {
+            referral = find_referral(child).or(referral);
+        }
+    }
+    referral
+}
+
+impl WhoisXmlParser for JsonWhoisParser {
+    fn parse_content<T: io::Read>(&self, json: T) -> Result<WhoisResult, ParseError> {
+        let value = JsonWhoisParser::read(json)?;
+        let nets = JsonWhoisParser::root(&value, "nets")?;
+        let mut warnings: Vec<ParseWarning> = Vec::new();
+
+        let mut ip_results: Vec<WhoisIpResult> = Vec::new();
+        for netref in elements(nets.get("netRef")) {
+            let ip_result = JsonWhoisParser::parse_netref(netref)?;
+            if let (Some(handle), Some(url)) = (ip_result.handle.clone(), text(netref)) {
+                if !url.trim().is_empty() {
+                    if let Some(warning) = StdWhoisXmlParser::check_netref_url(&handle, url) {
+                        self.warn(&mut warnings, warning)?;
+                    }
+                }
+            }
+            ip_results.push(ip_result);
+        }
+
+        let limit_exceeded = elements(nets.get("limitExceeded"))
+            .into_iter()
+            .find(|limit| text(limit).map_or(false, StdWhoisXmlParser::is_limit_exceeded))
+            .map(|limit| attribute(limit, "limit").map(String::from));
+        if let Some(limit) = limit_exceeded {
+            if !self.allow_partial {
+                return Err(ParseError::LimitExceeded { limit: limit });
+            }
+            warnings.push(ParseWarning::LimitExceeded { limit: limit });
+        }
+
+        let mut result = WhoisResult::new(ip_results);
+        result.warnings = warnings;
+        result.referral = find_referral(nets);
+        Ok(result)
+    }
+
+    fn parse_net_details<T: io::Read>(&self, json: T) -> Result<NetDetails, ParseError> {
+        let value = JsonWhoisParser::read(json)?;
+        let net = JsonWhoisParser::root(&value, "net")?;
+        let mut details = NetDetails::default();
+
+        details.org = net.get("orgRef").and_then(|org| attribute(org, "handle")).map(String::from);
+        if let Some(origin_ases) = net.get("originASes") {
+            for origin_as in elements(origin_ases.get("originAS")) {
+                if let Some(asn) = text(origin_as) {
+                    details.origin_ases.push(StdWhoisXmlParser::parse_asn(asn)?);
+                }
+            }
+        }
+        if let Some(comment) = net.get("comment") {
+            details.comments = elements(comment.get("line")).into_iter().filter_map(text).map(String::from).collect();
+        }
+
+        details.handle = net.get("handle").and_then(text).map(|s| s.trim().to_string());
+        details.name = net.get("name").and_then(text).map(|s| s.trim().to_string());
+        details.start_ip = match net.get("startAddress").and_then(text) {
+            Some(start_ip) => Some(StdWhoisXmlParser::parse_ip(start_ip)?),
+            None => None,
+        };
+        details.end_ip = match net.get("endAddress").and_then(text) {
+            Some(end_ip) => Some(StdWhoisXmlParser::parse_ip(end_ip)?),
+            None => None,
+        };
+        details.registration_date = net.get("registrationDate").and_then(text).map(|s| s.trim().to_string());
+        details.update_date = net.get("updateDate").and_then(text).map(|s| s.trim().to_string());
+        details.cidr_length = net.get("netBlocks")
+            .and_then(|blocks| elements(blocks.get("netBlock")).into_iter().next())
+            .and_then(|block| block.get("cidrLength"))
+            .and_then(text)
+            .and_then(|s| s.trim().parse::<u8>().ok());
+
+        let version = net.get("version").and_then(text).and_then(|s| s.trim().parse::<u8>().ok());
+        if let (Some(version), Some(address)) = (version, details.start_ip) {
+            if let Some(warning) = StdWhoisXmlParser::check_version(version, address) {
+                self.warn(&mut details.warnings, warning)?;
+            }
+        }
+        Ok(details)
+    }
+
+    fn parse_pocs<T: io::Read>(&self, json: T) -> Result<Vec<PocLink>, ParseError> {
+        let value = JsonWhoisParser::read(json)?;
+        let pocs = JsonWhoisParser::root(&value, "pocs")?;
+        let mut links = Vec::new();
+        for link in elements(pocs.get("pocLinkRef")) {
+            let handle = attribute(link, "handle")
+                .ok_or_else(|| ParseError::JsonError(String::from("pocLinkRef has no handle")))?;
+            let role = attribute(link, "description").or_else(|| attribute(link, "function")).unwrap_or("");
+            links.push(PocLink {
+                handle: handle.to_string(),
+                role: role.to_string(),
+            });
+        }
+        Ok(links)
+    }
+
+    fn parse_poc_emails<T: io::Read>(&self, json: T) -> Result<Vec<String>, ParseError> {
+        let value = JsonWhoisParser::read(json)?;
+        let poc = JsonWhoisParser::root(&value, "poc")?;
+        let emails = match poc.get("emails") {
+            Some(emails) => elements(emails.get("email")).into_iter().filter_map(text).map(String::from).collect(),
+            None => Vec::new(),
+        };
+        Ok(emails)
+    }
+
+    fn parse_asn_org<T: io::Read>(&self, json: T) -> Result<String, ParseError> {
+        let value = JsonWhoisParser::read(json)?;
+        let asn = JsonWhoisParser::root(&value, "asn")?;
+        asn.get("orgRef")
+            .and_then(|org| attribute(org, "handle"))
+            .map(String::from)
+            .ok_or_else(|| ParseError::JsonError(String::from("The AS number has no orgRef")))
+    }
+
+    fn accept(&self) -> &'static str {
+        "application/json"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::str::FromStr;
+
+    use super::JsonWhoisParser;
+    use xmlparser::{WhoisXmlParser, ParseError, ParseWarning};
+
+    const DROPBOX_NETS: &str = r#"{
+  "nets": {
+    "@termsOfUse": "https://www.arin.net/whois_tou.html",
+    "@inaccuracyReportUrl": "https://www.arin.net/public/whoisinaccuracy/index.xhtml",
+    "limitExceeded": {"@limit": "256", "$": "false"},
+    "netRef": [
+      {
+        "@endAddress": "162.125.255.255",
+        "@startAddress": "162.125.0.0",
+        "@handle": "NET-162-125-0-0-1",
+        "@name": "DROPB",
+        "$": "https://whois.arin.net/rest/net/NET-162-125-0-0-1"
+      },
+      {
+        "@endAddress": "2620:100:6fff:ffff:ffff:ffff:ffff:ffff",
+        "@startAddress": "2620:100:6000:0:0:0:0:0",
+        "@handle": "NET6-2620-100-6000-1",
+        "@name": "DROPBOX-V6",
+        "$": "https://whois.arin.net/rest/net/NET6-2620-100-6000-1"
+      }
+    ]
+  }
+}"#;
+
+    fn ranges(json: &str) -> Vec<(IpAddr, IpAddr)> {
+        JsonWhoisParser::new()
+            .parse_content(json.as_bytes())
+            .unwrap()
+            .ips
+            .iter()
+            .map(|ip| (ip.start_ip, ip.end_ip))
+            .collect()
+    }
+
+    #[test]
+    fn parse_content_nets() {
+        assert_eq!(ranges(DROPBOX_NETS),
+                   vec![(IpAddr::from_str("162.125.0.0").unwrap(), IpAddr::from_str("162.125.255.255").unwrap()),
+                        (IpAddr::from_str("2620:100:6000::").unwrap(),
+                         IpAddr::from_str("2620:100:6fff:ffff:ffff:ffff:ffff:ffff").unwrap())]);
+        let result = JsonWhoisParser::new().parse_content(DROPBOX_NETS.as_bytes()).unwrap();
+        assert_eq!(result.ips[0].name, "DROPB");
+        assert_eq!(result.ips[1].handle, Some(String::from("NET6-2620-100-6000-1")));
+        assert!(result.warnings.is_empty());
+        assert_eq!(result.referral, None);
+    }
+
+    #[test]
+    fn parse_content_single_netref() {
+        let json = r#"{"nets": {"limitExceeded": {"@limit": "256", "$": "false"},
+            "netRef": {"@endAddress": "162.125.255.255", "@startAddress": "162.125.0.0",
+                       "@handle": "NET-162-125-0-0-1", "@name": "DROPB",
+                       "$": "https://whois.arin.net/rest/net/NET-162-125-0-0-1"}}}"#;
+        assert_eq!(ranges(json),
+                   vec![(IpAddr::from_str("162.125.0.0").unwrap(), IpAddr::from_str("162.125.255.255").unwrap())]);
+        assert!(ranges(r#"{"nets": {"limitExceeded": {"@limit": "256", "$": "false"}}}"#).is_empty());
+    }
+
+    #[test]
+    fn parse_content_invalid() {
+        for json in &["", "<nets/>", "[]", r#"{"net": {}}"#] {
+            match JsonWhoisParser::new().parse_content(json.as_bytes()) {
+                Err(ParseError::JsonError(_)) => {}
+                other => panic!("Expected a JSON error for {:?}, got {:?}", json, other),
+            }
+        }
+        let json = r#"{"nets": {"netRef": {"@startAddress": "162.125.0.0", "@name": "DROPB"}}}"#;
+        assert!(JsonWhoisParser::new().parse_content(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parse_content_limit_exceeded() {
+        let json = r#"{"nets": {"limitExceeded": {"@limit": "256", "$": "true"},
+            "netRef": {"@endAddress": "162.125.255.255", "@startAddress": "162.125.0.0",
+                       "@handle": "NET-162-125-0-0-1", "@name": "DROPB"}}}"#;
+        match JsonWhoisParser::new().parse_content(json.as_bytes()) {
+            Err(ParseError::LimitExceeded { limit: Some(ref limit) }) if limit == "256" => {}
+            other => panic!("Expected the limit to be exceeded, got {:?}", other),
+        }
+        let result = JsonWhoisParser::new().allow_partial(true).parse_content(json.as_bytes()).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.warnings,
+                   vec![ParseWarning::LimitExceeded { limit: Some(String::from("256")) }]);
+
+        let json = r#"{"nets": {"limitExceeded": {"@limit": "256", "$": " True "}}}"#;
+        assert!(JsonWhoisParser::new().parse_content(json.as_bytes()).is_err());
+        for limit_exceeded in &["False", ""] {
+            let json = format!(r#"{{"nets": {{"limitExceeded": {{"@limit": "256", "$": "{}"}}}}}}"#, limit_exceeded);
+            assert!(JsonWhoisParser::new().parse_content(json.as_bytes()).unwrap().ips.is_empty());
+        }
+    }
+
+    #[test]
+    fn parse_content_handle_mismatch() {
+        let json = r#"{"nets": {"netRef": {"@endAddress": "162.125.255.255", "@startAddress": "162.125.0.0",
+            "@handle": "NET-162-125-0-0-1", "@name": "DROPB",
+            "$": "https://whois.arin.net/rest/net/NET-8-8-8-0-1"}}}"#;
+        let result = JsonWhoisParser::new().parse_content(json.as_bytes()).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert!(JsonWhoisParser::new().strict(true).parse_content(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parse_content_referral() {
+        let json = r#"{"nets": {"netRef": {"@endAddress": "2.255.255.255", "@startAddress": "2.0.0.0",
+            "@handle": "NET-2-0-0-0-1", "@name": "RIPE-CIDR-BLOCK"},
+            "orgRef": {"@handle": "RIPE", "@name": "RIPE Network Coordination Centre"}}}"#;
+        let result = JsonWhoisParser::new().parse_content(json.as_bytes()).unwrap();
+        assert_eq!(result.referral, Some(String::from("RIPE")));
+    }
+
+    #[test]
+    fn parse_net_details() {
+        let json = r#"{"net": {
+            "version": {"$": "4"},
+            "registrationDate": {"$": "2013-06-26T14:26:06-04:00"},
+            "startAddress": {"$": "162.125.0.0"},
+            "handle": {"$": "NET-162-125-0-0-1"},
+            "orgRef": {"@handle": "DROPB", "@name": "Dropbox, Inc.", "$": "https://whois.arin.net/rest/org/DROPB"},
+            "originASes": {"originAS": [{"$": "AS19679"}, {"$": "AS54372"}]},
+            "comment": {"line": {"@number": "0", "$": "Dropbox production network"}},
+            "netBlocks": {"netBlock": {"startAddress": {"$": "162.125.0.0"}, "cidrLength": {"$": "16"}}}
+        }}"#;
+        let details = JsonWhoisParser::new().parse_net_details(json.as_bytes()).unwrap();
+        assert_eq!(details.org, Some(String::from("DROPB")));
+        assert_eq!(details.origin_ases, vec![19679, 54372]);
+        assert_eq!(details.comments, vec![String::from("Dropbox production network")]);
+        assert_eq!(details.handle, Some(String::from("NET-162-125-0-0-1")));
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+        assert_eq!(details.end_ip, None);
+        assert_eq!(details.cidr_length, Some(16));
+        assert_eq!(details.registration_date, Some(String::from("2013-06-26T14:26:06-04:00")));
+        assert_eq!(details.update_date, None);
+        assert!(details.warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_net_details_version_mismatch() {
+        let json = r#"{"net": {"version": {"$": "6"}, "startAddress": {"$": "162.125.0.0"}}}"#;
+        let details = JsonWhoisParser::new().parse_net_details(json.as_bytes()).unwrap();
+        assert_eq!(details.warnings.len(), 1);
+        assert!(JsonWhoisParser::new().strict(true).parse_net_details(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parse_pocs() {
+        let json = r#"{"pocs": {"limitExceeded": {"@limit": "256", "$": "false"},
+            "pocLinkRef": [{"@description": "Abuse", "@function": "AB", "@handle": "NETWO7047-ARIN",
+                            "$": "https://whois.arin.net/rest/poc/NETWO7047-ARIN"},
+                           {"@description": "Admin", "@function": "AD", "@handle": "DROPB1-ARIN",
+                            "$": "https://whois.arin.net/rest/poc/DROPB1-ARIN"}]}}"#;
+        let pocs: Vec<(String, String)> = JsonWhoisParser::new()
+            .parse_pocs(json.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|poc| (poc.handle, poc.role))
+            .collect();
+        assert_eq!(pocs,
+                   vec![(String::from("NETWO7047-ARIN"), String::from("Abuse")),
+                        (String::from("DROPB1-ARIN"), String::from("Admin"))]);
+    }
+
+    #[test]
+    fn parse_poc_emails() {
+        let json = r#"{"poc": {"emails": {"email": {"$": "abuse@dropbox.com"}},
+            "handle": {"$": "NETWO7047-ARIN"}, "lastName": {"$": "Network Abuse"}}}"#;
+        assert_eq!(JsonWhoisParser::new().parse_poc_emails(json.as_bytes()).unwrap(),
+                   vec![String::from("abuse@dropbox.com")]);
+    }
+
+    #[test]
+    fn parse_asn_org() {
+        let json = r#"{"asn": {"handle": {"$": "AS19679"}, "name": {"$": "DROPBOX"},
+            "orgRef": {"@handle": "DROPB", "@name": "Dropbox, Inc.", "$": "https://whois.arin.net/rest/org/DROPB"},
+            "startAsNumber": {"$": "19679"}, "endAsNumber": {"$": "19679"}}}"#;
+        assert_eq!(JsonWhoisParser::new().parse_asn_org(json.as_bytes()), Ok(String::from("DROPB")));
+        assert!(JsonWhoisParser::new().parse_asn_org(r#"{"asn": {}}"#.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn accept() {
+        assert_eq!(JsonWhoisParser::new().accept(), "application/json");
+    }
+}
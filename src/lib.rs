@@ -0,0 +1,1370 @@
+//! Look up the IPv4/6 address ranges ARIN has assigned to a company.
+//!
+//! A `WhoisCompanyIpsClient` is made of a `WhoisHttpClient` to fetch with,
+//! usually `StdWhoisHttpClient`, and a `WhoisXmlParser` for ARIN's
+//! responses. Any client will do, e.g. one answering from a cache:
+//!
+//! ```
+//! use whois_ips::{WhoisCompanyIpsClient, WhoisHttpClient, HttpClientError, StdWhoisXmlParser};
+//!
+//! struct CannedHttpClient;
+//!
+//! impl WhoisHttpClient for CannedHttpClient {
+//!     fn get_content(&self, _url: &str) -> Result<String, HttpClientError> {
+//!         Ok(String::from(r#"<?xml version="1.0"?>
+//! <nets xmlns="http://www.arin.net/whoisrws/core/v1">
+//!   <limitExceeded limit="256">false</limitExceeded>
+//!   <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+//! </nets>"#))
+//!     }
+//! }
+//!
+//! let client = WhoisCompanyIpsClient::from_parts(CannedHttpClient, StdWhoisXmlParser::new());
+//! let result = client.get("DROPB").unwrap();
+//! assert_eq!(result.ips.len(), 1);
+//! assert_eq!(result.ips[0].name, "DROPB");
+//! assert_eq!(result.ips[0].start_ip.to_string(), "162.125.0.0");
+//! ```
+
+#![cfg_attr(feature="clippy", feature(plugin))]
+#![cfg_attr(feature="clippy", plugin(clippy))]
+
+extern crate hyper;
+extern crate hyper_native_tls;
+extern crate native_tls;
+extern crate xml;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate sha2;
+#[macro_use]
+extern crate log;
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::io;
+
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+pub mod batch;
+
+pub mod bogons;
+
+pub mod bulk;
+pub use bulk::BulkFileParser;
+
+pub mod cache;
+
+pub mod cidr;
+use cidr::{CidrIter, IpNet};
+
+pub mod demo;
+
+pub mod error;
+pub use error::{WhoisError, WhoisErrorKind};
+
+pub mod hierarchy;
+
+pub mod httpclient;
+pub use httpclient::{WhoisHttpClient, StdWhoisHttpClient, HttpClientError, FetchMeta};
+use httpclient::redact_url;
+
+pub mod xmlparser;
+pub use xmlparser::{WhoisXmlParser, StdWhoisXmlParser, WhoisParser, NetDetails, ParseError, ParseWarning, PocLink};
+
+pub mod jsonparser;
+pub use jsonparser::JsonWhoisParser;
+
+pub mod ripeparser;
+pub use ripeparser::RipeXmlParser;
+
+pub mod inflight;
+
+pub mod output;
+use output::{OutputFormat, OutputOptions, Explain};
+
+pub mod rdapparser;
+pub use rdapparser::RdapJsonParser;
+
+pub mod ranges;
+use ranges::{DedupKey, Family, SortOrder};
+
+pub mod registry;
+pub use registry::Registry;
+
+pub mod retry;
+
+pub mod source;
+pub use source::WhoisSource;
+
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhoisIpResult {
+    pub name: String,
+    pub start_ip: IpAddr,
+    pub end_ip: IpAddr,
+    /// ARIN's handle for the net, e.g. `NET-162-125-0-0-1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// Handle of the net this one is reassigned from, see `--include-children`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Handle of the org the net is registered to, only known with `--resolve-details`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+    /// ASNs announcing the net, only known with `--resolve-details`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub origin_ases: Vec<u32>,
+    /// Free text remarks on the net, only known with `--resolve-details`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<String>,
+    /// Name of the `WhoisSource` that returned this range, see `WhoisSource::name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Email of the org's abuse contact, only known with `--with-abuse-contact`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abuse_email: Option<String>,
+    /// ARIN's prefix length for the net's first `netBlock`, only known with
+    /// `--resolve-details`. See `prefix_len`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cidr_length: Option<u8>,
+    /// When the net was registered, as ARIN gives it, e.g.
+    /// `2013-06-26T14:26:06-04:00`. Only known with `--resolve-details`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_date: Option<String>,
+    /// When the net's record last changed, only known with `--resolve-details`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_date: Option<String>,
+    /// The handle whose lookup returned this range, only set when several
+    /// handles are looked up at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<String>,
+}
+
+impl WhoisIpResult {
+    pub fn new(name: String, start_ip: IpAddr, end_ip: IpAddr) -> WhoisIpResult {
+        WhoisIpResult {
+            name: name,
+            start_ip: start_ip,
+            end_ip: end_ip,
+            handle: None,
+            parent: None,
+            org: None,
+            origin_ases: Vec::new(),
+            comments: Vec::new(),
+            source: None,
+            abuse_email: None,
+            cidr_length: None,
+            registration_date: None,
+            update_date: None,
+            lookup: None,
+        }
+    }
+
+    /// The family of the range's addresses, going by `start_ip`.
+    pub fn family(&self) -> Family {
+        Family::of(self.start_ip)
+    }
+
+    /// Number of addresses between `start_ip` and `end_ip` inclusive.
+    ///
+    /// A range whose start and end are of different address families, or
+    /// whose end is before its start, covers no addresses.
+    pub fn address_count(&self) -> u128 {
+        match (self.start_ip, self.end_ip) {
+            (IpAddr::V4(start), IpAddr::V4(end)) => {
+                let (start, end) = (u32::from(start) as u128, u32::from(end) as u128);
+                if end < start { 0 } else { end - start + 1 }
+            }
+            (IpAddr::V6(start), IpAddr::V6(end)) => {
+                let (start, end) = (u128::from(start), u128::from(end));
+                if end < start {
+                    0
+                } else {
+                    // The whole IPv6 space has 2^128 addresses, one more than fits.
+                    (end - start).saturating_add(1)
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    /// The range's prefix length, ARIN's `cidr_length` if it gave one, or
+    /// else the range's own if it is exactly one CIDR prefix.
+    pub fn prefix_len(&self) -> Option<u8> {
+        if self.cidr_length.is_some() {
+            return self.cidr_length;
+        }
+        let mut cidrs = self.cidrs();
+        match (cidrs.next(), cidrs.next()) {
+            (Some(net), None) => Some(net.prefix_len()),
+            _ => None,
+        }
+    }
+
+    /// The fewest CIDR prefixes that exactly cover this range.
+    pub fn cidrs(&self) -> CidrIter {
+        cidr::decompose(self.start_ip, self.end_ip)
+    }
+
+    /// Split the range into the part below `boundary` and the part at or
+    /// above it; either part is `None` if it would be empty.
+    ///
+    /// A `boundary` of the other address family never falls inside the
+    /// range, so the whole range is returned on the side the IPv4 before
+    /// IPv6 ordering puts it.
+    pub fn split_at(&self, boundary: IpAddr) -> (Option<WhoisIpResult>, Option<WhoisIpResult>) {
+        if boundary.is_ipv4() != self.start_ip.is_ipv4() {
+            return if boundary < self.start_ip {
+                (None, Some(self.clone()))
+            } else {
+                (Some(self.clone()), None)
+            };
+        }
+        if boundary <= self.start_ip {
+            return (None, Some(self.clone()));
+        }
+        if boundary > self.end_ip {
+            return (Some(self.clone()), None);
+        }
+
+        let mut below = self.clone();
+        below.end_ip = ranges::prev_addr(boundary).unwrap();
+        let mut above = self.clone();
+        above.start_ip = boundary;
+        (Some(below), Some(above))
+    }
+}
+
+/// How a result's ranges differ from an earlier result's, going only by
+/// their start and end addresses. See `WhoisResult::diff`.
+#[derive(Debug, Default)]
+pub struct ResultDiff {
+    /// Ranges that are new since the earlier result
+    pub added: Vec<WhoisIpResult>,
+    /// Ranges of the earlier result that are gone
+    pub removed: Vec<WhoisIpResult>,
+    pub unchanged: Vec<WhoisIpResult>,
+}
+
+#[derive(Debug)]
+pub struct WhoisResult {
+    pub ips: Vec<WhoisIpResult>,
+    pub warnings: Vec<ParseWarning>,
+    /// Handle of the registry that manages this space instead, if any
+    pub referral: Option<String>,
+    /// The requests made to get this result
+    pub fetches: Vec<FetchMeta>,
+}
+
+impl WhoisResult {
+    pub fn new(ips: Vec<WhoisIpResult>) -> WhoisResult {
+        WhoisResult {
+            ips: ips,
+            warnings: Vec::new(),
+            referral: None,
+            fetches: Vec::new(),
+        }
+    }
+
+    /// Total number of CIDR prefixes needed to cover every range, i.e. how
+    /// many rules an ACL built from this result would have.
+    pub fn cidr_count(&self) -> usize {
+        self.ips.iter().map(|ip| ip.cidrs().count()).sum()
+    }
+
+    /// Replace the ranges with the fewest CIDR prefixes covering the same
+    /// addresses, see `cidr::aggregate`.
+    ///
+    /// Each prefix keeps the name of the range its first address came from.
+    pub fn aggregate_cidrs(&mut self) {
+        let nets: Vec<IpNet> = self.ips.iter().flat_map(|ip| ip.cidrs()).collect();
+        let aggregated = cidr::aggregate(&nets)
+            .into_iter()
+            .map(|net| {
+                let mut ip = self.ips
+                    .iter()
+                    .find(|ip| ip.start_ip <= net.addr() && net.addr() <= ip.end_ip)
+                    .unwrap()
+                    .clone();
+                ip.start_ip = net.addr();
+                ip.end_ip = net.last_addr();
+                ip
+            })
+            .collect();
+        self.ips = aggregated;
+    }
+
+    /// Apply the requested transformations in order, noting how many ranges
+    /// are left after each in `explain`.
+    pub fn transform(&mut self, transforms: &Transforms, explain: &mut Explain) {
+        if let Some(family) = transforms.only_family {
+            self.retain_family(family);
+            explain.step("after family filter", self.ips.len());
+        }
+        if let Some(family) = transforms.prefer_family {
+            self.retain_family_if_both(family);
+            explain.step("after prefer family", self.ips.len());
+        }
+        if let Some(key) = transforms.dedup {
+            match key {
+                DedupKey::Range => ranges::dedup_by_precedence(&mut self.ips, &transforms.rir_precedence),
+                DedupKey::Handle => ranges::dedup_by_handle(&mut self.ips),
+            }
+            explain.step("after dedup", self.ips.len());
+        }
+        if transforms.merge {
+            let ips = self.ips.split_off(0);
+            self.ips = ranges::merge(ips);
+            explain.step("after merge", self.ips.len());
+        }
+        if transforms.aggregate {
+            self.aggregate_cidrs();
+            explain.step("after aggregate", self.ips.len());
+        }
+        if let Some(order) = transforms.sort_order {
+            ranges::sort_in(&mut self.ips, order);
+        }
+        if let Some(count) = transforms.sample {
+            if transforms.sort_order.is_none() {
+                ranges::sort(&mut self.ips);
+            }
+            let ips = self.ips.split_off(0);
+            self.ips = ranges::sample(ips, count);
+            explain.step("after sample", self.ips.len());
+        }
+        if transforms.ascii_names {
+            for ip in &mut self.ips {
+                ip.name = output::escape_non_ascii(&ip.name);
+            }
+        }
+    }
+
+    /// A result holding the same ranges, ordered as `--sort-by start` prints
+    /// them: IPv4 ranges before IPv6 ranges, then by start address and end
+    /// address compared numerically within a family, so `9.0.0.0` comes
+    /// before `10.0.0.0` and `2001:db8::a` before `2001:db8::1:0`. Ranges
+    /// with the same bounds keep their order.
+    pub fn sorted(&self) -> WhoisResult {
+        let mut ips = self.ips.clone();
+        ranges::sort(&mut ips);
+        WhoisResult::new(ips)
+    }
+
+    /// The prefixes in `supernet` not covered by any range.
+    pub fn complement_within(&self, supernet: IpNet) -> Vec<IpNet> {
+        let ranges: Vec<(IpAddr, IpAddr)> = self.ips.iter().map(|ip| (ip.start_ip, ip.end_ip)).collect();
+        cidr::complement(supernet, &ranges)
+    }
+
+    /// Keep only the ranges of `family`, going by their start addresses.
+    pub fn retain_family(&mut self, family: Family) {
+        self.ips.retain(|ip| ip.family() == family);
+    }
+
+    /// Keep only the ranges of the `primary` family if there are any,
+    /// otherwise keep the ranges of the other family.
+    pub fn retain_family_if_both(&mut self, primary: Family) {
+        if self.ips.iter().any(|ip| ip.family() == primary) {
+            self.ips.retain(|ip| ip.family() == primary);
+        }
+    }
+
+    /// Hex SHA-256 of the distinct ranges in address order, so results with
+    /// the same ranges hash the same however they are ordered or named.
+    pub fn content_hash(&self) -> String {
+        let mut ips = self.ips.clone();
+        ranges::dedup(&mut ips);
+        ranges::sort(&mut ips);
+        let mut hasher = Sha256::new();
+        for ip in &ips {
+            hasher.input(format!("{}-{}\n", ip.start_ip, ip.end_ip));
+        }
+        format!("{:x}", hasher.result())
+    }
+
+    /// Compare with an `earlier` result of the same lookup. Each of the
+    /// diff's lists holds a range at most once, in the order first seen.
+    pub fn diff(&self, earlier: &WhoisResult) -> ResultDiff {
+        let bounds = |ip: &WhoisIpResult| (ip.start_ip, ip.end_ip);
+        let earlier_ranges: HashSet<(IpAddr, IpAddr)> = earlier.ips.iter().map(&bounds).collect();
+        let ranges: HashSet<(IpAddr, IpAddr)> = self.ips.iter().map(&bounds).collect();
+        let mut diff = ResultDiff::default();
+        for ip in &self.ips {
+            if earlier_ranges.contains(&bounds(ip)) {
+                diff.unchanged.push(ip.clone());
+            } else {
+                diff.added.push(ip.clone());
+            }
+        }
+        diff.removed = earlier.ips.iter().filter(|ip| !ranges.contains(&bounds(ip))).cloned().collect();
+        ranges::dedup(&mut diff.added);
+        ranges::dedup(&mut diff.removed);
+        ranges::dedup(&mut diff.unchanged);
+        diff
+    }
+
+    /// Sum of `address_count` over every range, saturating at `u128::MAX`.
+    pub fn total_address_count(&self) -> u128 {
+        self.ips.iter().fold(0u128, |total, ip| total.saturating_add(ip.address_count()))
+    }
+}
+
+/// Serializes as the array of its ranges, the shape `--format json` prints.
+impl Serialize for WhoisResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.ips.serialize(serializer)
+    }
+}
+
+/// The optional changes made to a result before it is printed.
+///
+/// They are applied in the order of the fields. Merging sorts by start
+/// address itself, so a requested `sort_order` still decides the final order.
+#[derive(Debug, Default)]
+pub struct Transforms {
+    /// Keep only the ranges of this family, even if that leaves none
+    pub only_family: Option<Family>,
+    pub prefer_family: Option<Family>,
+    pub dedup: Option<DedupKey>,
+    /// Registry names, most preferred first, deciding which of the same
+    /// range from several registries survives dedup
+    pub rir_precedence: Vec<String>,
+    pub merge: bool,
+    pub aggregate: bool,
+    pub sort_order: Option<SortOrder>,
+    pub sample: Option<usize>,
+    pub ascii_names: bool,
+}
+
+impl Transforms {
+    /// What `--normalize` asks for: dedup, sort and merge, keeping any
+    /// other dedup key or sort order already chosen.
+    pub fn normalize(&mut self) {
+        if self.dedup.is_none() {
+            self.dedup = Some(DedupKey::Range);
+        }
+        self.merge = true;
+        if self.sort_order.is_none() {
+            self.sort_order = Some(SortOrder::Start);
+        }
+    }
+}
+
+// https://www.arin.net/resources/whoisrws/whois_api.html
+/// Base of ARIN's Whois-RWS REST API. ARIN redirects plain HTTP here, and
+/// some networks block it outright.
+pub const ARIN_REST_URL: &str = "https://whois.arin.net/rest";
+
+/// How many levels of reassignments `--include-children` descends.
+pub const MAX_CHILD_DEPTH: usize = 3;
+
+/// Looks companies up in ARIN's Whois-RWS API, or another `Registry`'s,
+/// fetching with `C` and parsing the responses with `P`.
+///
+/// Only `get` and `get_url` work for registries other than ARIN.
+pub struct WhoisCompanyIpsClient<C: WhoisHttpClient, P: WhoisXmlParser> {
+    client: C,
+    parser: P,
+    registry: Registry,
+}
+
+impl<C: WhoisHttpClient, P: WhoisXmlParser> WhoisCompanyIpsClient<C, P> {
+    pub fn from_parts(client: C, parser: P) -> WhoisCompanyIpsClient<C, P> {
+        WhoisCompanyIpsClient {
+            client: client,
+            parser: parser,
+            registry: Registry::default(),
+        }
+    }
+
+    /// Look companies up in `registry` instead of ARIN. `P` must parse that
+    /// registry's responses, e.g. `RipeXmlParser` for `Registry::Ripe` or
+    /// `RdapJsonParser` for `Registry::Lacnic`.
+    pub fn registry(mut self, registry: Registry) -> WhoisCompanyIpsClient<C, P> {
+        self.registry = registry;
+        self
+    }
+
+    /// Look up the nets registered to the org handle `company`, e.g. `DROPB`.
+    pub fn get(&self, company: &str) -> Result<WhoisResult, WhoisError> {
+        let url = self.registry.org_nets_url(company);
+        self.get_url(&url)
+    }
+
+    /// The handle of the org the AS number `asn` is registered to.
+    pub fn asn_org(&self, asn: u32) -> Result<String, WhoisError> {
+        let url = format!("{}/asn/AS{}", ARIN_REST_URL, asn);
+        let http_response = self.client.get_content(&url)?;
+        Ok(self.parser.parse_asn_org(http_response.as_bytes())?)
+    }
+
+    /// Look up the nets of the org the AS number `asn` is registered to,
+    /// always following the AS number's org reference. See `asn_org` for
+    /// the org alone.
+    pub fn get_asn(&self, asn: u32) -> Result<WhoisResult, WhoisError> {
+        let org = self.asn_org(asn)?;
+        self.get(&org)
+    }
+
+    /// Look up the net `ip` belongs to, the most specific one ARIN has.
+    pub fn get_ip(&self, ip: IpAddr) -> Result<WhoisResult, WhoisError> {
+        let url = format!("{}/ip/{}", ARIN_REST_URL, ip);
+        let (http_response, meta) = self.client.fetch(&url)?;
+        let details = self.parser.parse_net_details(http_response.as_bytes())?;
+        let (start_ip, end_ip) = match (details.start_ip, details.end_ip) {
+            (Some(start_ip), Some(end_ip)) => (start_ip, end_ip),
+            _ => return Err(WhoisError::Parse(ParseError::MissingAddress(details.handle))),
+        };
+        let name = match details.name {
+            Some(name) => name,
+            None => details.handle.clone().unwrap_or_default(),
+        };
+        let mut net = WhoisIpResult::new(name, start_ip, end_ip);
+        net.handle = details.handle;
+        net.org = details.org;
+        net.origin_ases = details.origin_ases;
+        net.comments = details.comments;
+        net.cidr_length = details.cidr_length;
+        net.registration_date = details.registration_date;
+        net.update_date = details.update_date;
+        let mut result = WhoisResult::new(vec![net]);
+        result.warnings = details.warnings;
+        result.fetches.push(meta);
+        Ok(result)
+    }
+
+    /// Add the nets reassigned from each net in `result`, and theirs in turn,
+    /// down to `max_depth` levels.
+    ///
+    /// A net is only fetched once, so child references that loop back are
+    /// ignored. Nets ARIN has no children for are skipped.
+    pub fn include_children(&self, result: &mut WhoisResult, max_depth: usize) -> Result<(), WhoisError> {
+        let mut visited: HashSet<String> = result.ips.iter().filter_map(|ip| ip.handle.clone()).collect();
+        let mut parents: Vec<String> = result.ips.iter().filter_map(|ip| ip.handle.clone()).collect();
+        for _ in 0..max_depth {
+            let mut children = Vec::new();
+            for parent in &parents {
+                let url = format!("{}/net/{}/children", ARIN_REST_URL, parent);
+                let mut response = match self.get_url(&url) {
+                    Ok(response) => response,
+                    Err(ref err) if err.kind() == WhoisErrorKind::NotFound => continue,
+                    Err(err) => return Err(err),
+                };
+                result.fetches.append(&mut response.fetches);
+                result.warnings.append(&mut response.warnings);
+                for mut ip in response.ips {
+                    ip.parent = Some(parent.clone());
+                    match ip.handle.clone() {
+                        Some(ref handle) if visited.contains(handle) => {}
+                        Some(handle) => {
+                            visited.insert(handle.clone());
+                            children.push(handle);
+                            result.ips.push(ip);
+                        }
+                        None => result.ips.push(ip),
+                    }
+                }
+            }
+            if children.is_empty() {
+                break;
+            }
+            parents = children;
+        }
+        Ok(())
+    }
+
+    /// Fetch each net's own record and add its `NetDetails` to the net.
+    pub fn resolve_details(&self, result: &mut WhoisResult) -> Result<(), WhoisError> {
+        for ip in &mut result.ips {
+            let url = match ip.handle {
+                Some(ref handle) => format!("{}/net/{}", ARIN_REST_URL, handle),
+                None => continue,
+            };
+            let (http_response, meta) = self.client.fetch(&url)?;
+            let details = self.parser.parse_net_details(http_response.as_bytes())?;
+            result.fetches.push(meta);
+            ip.org = details.org;
+            ip.origin_ases = details.origin_ases;
+            ip.comments = details.comments;
+            ip.cidr_length = details.cidr_length;
+            ip.registration_date = details.registration_date;
+            ip.update_date = details.update_date;
+            result.warnings.extend(details.warnings);
+        }
+        Ok(())
+    }
+
+    /// Set each net's `abuse_email` to the first email of its org's abuse
+    /// contact. Nets without an `org`, see `resolve_details`, are skipped.
+    ///
+    /// Each org is looked up once however many of the nets it has.
+    pub fn resolve_abuse_contacts(&self, result: &mut WhoisResult) -> Result<(), WhoisError> {
+        let mut abuse_emails: HashMap<String, Option<String>> = HashMap::new();
+        for ip in &mut result.ips {
+            let org = match ip.org {
+                Some(ref org) => org.clone(),
+                None => continue,
+            };
+            if !abuse_emails.contains_key(&org) {
+                let email = self.abuse_email(&org)?;
+                abuse_emails.insert(org.clone(), email);
+            }
+            ip.abuse_email = abuse_emails[&org].clone();
+        }
+        Ok(())
+    }
+
+    fn abuse_email(&self, org: &str) -> Result<Option<String>, WhoisError> {
+        let abuse = self.pocs(org)?.into_iter().find(|poc| poc.role.eq_ignore_ascii_case("abuse"));
+        let abuse = match abuse {
+            Some(abuse) => abuse,
+            None => return Ok(None),
+        };
+        let url = format!("{}/poc/{}", ARIN_REST_URL, abuse.handle);
+        let http_response = self.client.get_content(&url)?;
+        Ok(self.parser.parse_poc_emails(http_response.as_bytes())?.into_iter().next())
+    }
+
+    /// The points of contact linked to the org handle `company`, such as
+    /// its abuse contact.
+    pub fn pocs(&self, company: &str) -> Result<Vec<PocLink>, WhoisError> {
+        let url = format!("{}/org/{}/pocs", ARIN_REST_URL, company);
+        let http_response = self.client.get_content(&url)?;
+        Ok(self.parser.parse_pocs(http_response.as_bytes())?)
+    }
+
+    /// Check ARIN can be reached by fetching ARIN's own, always present, org
+    /// record. The response is not parsed.
+    pub fn health_check(&self) -> Result<(), WhoisError> {
+        let url = format!("{}/org/ARIN", ARIN_REST_URL);
+        self.client.fetch(&url)?;
+        Ok(())
+    }
+
+    /// Look `company` up and write its ranges to `w` in `format`, as they
+    /// come from ARIN without any of the binary's transformations.
+    pub fn write_to<W: io::Write>(&self,
+                                  company: &str,
+                                  format: OutputFormat,
+                                  options: &OutputOptions,
+                                  w: &mut W)
+                                  -> Result<(), WhoisError> {
+        let result = self.get(company)?;
+        output::write_result(&result, format, options, w)?;
+        Ok(())
+    }
+
+    /// Fetch and parse an ARIN REST URL as is, e.g. a `netRef`'s link.
+    ///
+    /// Only `http` and `https` URLs with a host are fetched.
+    pub fn get_url(&self, url: &str) -> Result<WhoisResult, WhoisError> {
+        validate_url(url)?;
+        let (http_response, meta) = self.client.fetch(url)?;
+        let mut parsed_response = self.parser.parse_content(http_response.as_bytes())?;
+        info!("Parsed {} ranges from {}", parsed_response.ips.len(), redact_url(url));
+        parsed_response.fetches.push(meta);
+        Ok(parsed_response)
+    }
+}
+
+impl<C: WhoisHttpClient, P: WhoisXmlParser> WhoisSource for WhoisCompanyIpsClient<C, P> {
+    fn name(&self) -> &str {
+        self.registry.name()
+    }
+
+    fn lookup(&self, handle: &str) -> Result<WhoisResult, WhoisError> {
+        self.get(handle)
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), WhoisError> {
+    let lower = url.to_lowercase();
+    let rest = if lower.starts_with("https://") {
+        &url[8..]
+    } else if lower.starts_with("http://") {
+        &url[7..]
+    } else {
+        return Err(WhoisError::InvalidUrl(url.to_string()));
+    };
+    let authority = rest.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or("");
+    // Credentials in the URL could be used to smuggle a different host past a quick read
+    if authority.is_empty() || authority.contains('@') {
+        return Err(WhoisError::InvalidUrl(url.to_string()));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use hyper::status::StatusCode;
+    use serde_json;
+
+    use super::{WhoisCompanyIpsClient, WhoisResult, WhoisIpResult, Transforms, MAX_CHILD_DEPTH};
+    use ranges::{DedupKey, Family, SortOrder};
+    use cidr::IpNet;
+    use error::{WhoisError, WhoisErrorKind};
+    use output::{self, Explain, OutputFormat, OutputOptions};
+    use httpclient::{WhoisHttpClient, HttpClientError};
+    use xmlparser::{StdWhoisXmlParser, ParseError};
+    use registry::Registry;
+    use ripeparser::RipeXmlParser;
+    use rdapparser::RdapJsonParser;
+    use source::{self, WhoisSource};
+
+    /// Serves canned bodies keyed on the full URL, and 404 for anything else.
+    struct MockHttpClient {
+        responses: HashMap<String, String>,
+        /// Every URL asked for, in order
+        requested: RefCell<Vec<String>>,
+    }
+
+    impl MockHttpClient {
+        fn new(responses: &[(&str, &str)]) -> MockHttpClient {
+            MockHttpClient {
+                responses: responses.iter()
+                    .map(|&(url, body)| (url.to_string(), body.to_string()))
+                    .collect(),
+                requested: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl WhoisHttpClient for MockHttpClient {
+        fn get_content(&self, url: &str) -> Result<String, HttpClientError> {
+            self.requested.borrow_mut().push(url.to_string());
+            self.responses
+                .get(url)
+                .cloned()
+                .ok_or(HttpClientError::HttpError(StatusCode::NotFound))
+        }
+    }
+
+    const DROPBOX_NETS: &str = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+</nets>
+"#;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    fn range(name: &str, start: &str, end: &str) -> WhoisIpResult {
+        WhoisIpResult::new(String::from(name), ip(start), ip(end))
+    }
+
+    fn dropbox() -> WhoisIpResult {
+        range("DROPB", "162.125.0.0", "162.125.255.255")
+    }
+
+    fn bounds(range: Option<WhoisIpResult>) -> Option<(IpAddr, IpAddr)> {
+        range.map(|r| (r.start_ip, r.end_ip))
+    }
+
+    #[test]
+    fn get_url() {
+        let url = "https://whois.arin.net/rest/org/DROPB/nets";
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(url, DROPBOX_NETS)]),
+                                                       StdWhoisXmlParser::new());
+        let result = client.get_url(url).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].start_ip, ip("162.125.0.0"));
+
+        match client.get_url("https://whois.arin.net/rest/org/NOSUCHORG/nets") {
+            Err(WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound))) => {}
+            other => panic!("Expected a 404, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn get_errors() {
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[("https://whois.arin.net/rest/org/BROKEN/nets",
+                                                                              "<nets><netRef")]),
+                                                       StdWhoisXmlParser::new());
+        match client.get("NOSUCHORG") {
+            Err(WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound))) => {}
+            other => panic!("Expected a 404, got {:?}", other),
+        }
+        match client.get("BROKEN") {
+            Err(WhoisError::Parse(ParseError::XmlError(_))) => {}
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn get_url_rejects_other_schemes() {
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[("file:///etc/passwd", DROPBOX_NETS)]),
+                                                       StdWhoisXmlParser::new());
+        for url in &["file:///etc/passwd", "ftp://whois.arin.net/", "whois.arin.net/rest/org/DROPB",
+                     "https:///rest/org/DROPB", "https://user@evil.example/"] {
+            match client.get_url(url) {
+                Err(WhoisError::InvalidUrl(_)) => {}
+                other => panic!("Expected {} to be refused, got {:?}", url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn transform_explain() {
+        let mut result = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255"),
+                                               range("B", "10.0.1.0", "10.0.1.255"),
+                                               range("C", "192.0.2.0", "192.0.2.255")]);
+        let transforms = Transforms {
+            aggregate: true,
+            sample: Some(1),
+            ..Transforms::default()
+        };
+        let mut explain = Explain::new();
+        explain.step("nets parsed", result.ips.len());
+        result.transform(&transforms, &mut explain);
+        explain.step("output", result.ips.len());
+
+        let mut out = Vec::new();
+        explain.write(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "nets parsed: 3\nafter aggregate: 2\nafter sample: 1\noutput: 1\n");
+    }
+
+    #[test]
+    fn transform_normalize() {
+        let ranges = || {
+            vec![range("B", "10.0.1.0", "10.0.1.255"),
+                 range("A", "10.0.0.0", "10.0.0.255"),
+                 range("B", "10.0.1.0", "10.0.1.255"),
+                 range("C", "192.0.2.0", "192.0.2.255")]
+        };
+        let bounds = |result: &WhoisResult| -> Vec<(String, IpAddr, IpAddr)> {
+            result.ips.iter().map(|ip| (ip.name.clone(), ip.start_ip, ip.end_ip)).collect()
+        };
+
+        let mut normalized = WhoisResult::new(ranges());
+        let mut transforms = Transforms::default();
+        transforms.normalize();
+        normalized.transform(&transforms, &mut Explain::new());
+
+        let mut combined = WhoisResult::new(ranges());
+        let transforms = Transforms {
+            dedup: Some(DedupKey::Range),
+            merge: true,
+            sort_order: Some(SortOrder::Start),
+            ..Transforms::default()
+        };
+        combined.transform(&transforms, &mut Explain::new());
+
+        assert_eq!(bounds(&normalized), bounds(&combined));
+        assert_eq!(bounds(&normalized),
+                   vec![(String::from("A"), ip("10.0.0.0"), ip("10.0.1.255")),
+                        (String::from("C"), ip("192.0.2.0"), ip("192.0.2.255"))]);
+    }
+
+    #[test]
+    fn sorted_output() {
+        let mut result = WhoisResult::new(vec![range("V6", "2620:100::", "2620:100::ff"),
+                                               range("DROPB", "162.125.0.0", "162.125.255.255"),
+                                               range("TEN", "10.0.0.0", "10.0.0.255")]);
+        let transforms = Transforms {
+            sort_order: Some(SortOrder::Start),
+            ..Transforms::default()
+        };
+        result.transform(&transforms, &mut Explain::new());
+        let mut out = Vec::new();
+        output::write_result(&result, OutputFormat::Plain, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "10.0.0.0/24\n162.125.0.0/16\n2620:100::/120\n");
+    }
+
+    #[test]
+    fn write_to_matches_write_result() {
+        let url = "https://whois.arin.net/rest/org/DROPB/nets";
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(url, DROPBOX_NETS)]),
+                                                       StdWhoisXmlParser::new());
+        for format in &[OutputFormat::Plain, OutputFormat::Json, OutputFormat::Cisco] {
+            let mut written = Vec::new();
+            client.write_to("DROPB", *format, &OutputOptions::default(), &mut written).unwrap();
+            let mut expected = Vec::new();
+            output::write_result(&client.get("DROPB").unwrap(), *format, &OutputOptions::default(), &mut expected)
+                .unwrap();
+            assert!(!written.is_empty());
+            assert_eq!(written, expected);
+        }
+        assert!(client.write_to("NOSUCHORG", OutputFormat::Plain, &OutputOptions::default(), &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn get_url_records_fetch() {
+        let url = "https://whois.arin.net/rest/org/DROPB/nets";
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(url, DROPBOX_NETS)]),
+                                                       StdWhoisXmlParser::new());
+        let result = client.get_url(url).unwrap();
+        assert_eq!(result.fetches.len(), 1);
+        assert_eq!(result.fetches[0].url, url);
+        assert_eq!(result.fetches[0].status, StatusCode::Ok);
+    }
+
+    const PARENT_CHILDREN: &str = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="162.125.127.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-2" name="DROPB-A">https://whois.arin.net/rest/net/NET-162-125-0-0-2</netRef>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.128.0" handle="NET-162-125-128-0-1" name="DROPB-B">https://whois.arin.net/rest/net/NET-162-125-128-0-1</netRef>
+</nets>
+"#;
+
+    // Refers back to the parent, which must not be added again
+    const CHILD_CHILDREN: &str = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+</nets>
+"#;
+
+    #[test]
+    fn include_children() {
+        let responses = [("https://whois.arin.net/rest/org/DROPB/nets", DROPBOX_NETS),
+                         ("https://whois.arin.net/rest/net/NET-162-125-0-0-1/children", PARENT_CHILDREN),
+                         ("https://whois.arin.net/rest/net/NET-162-125-0-0-2/children", CHILD_CHILDREN)];
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&responses), StdWhoisXmlParser::new());
+        let mut result = client.get("DROPB").unwrap();
+        client.include_children(&mut result, MAX_CHILD_DEPTH).unwrap();
+        let names: Vec<&str> = result.ips.iter().map(|ip| ip.name.as_ref()).collect();
+        assert_eq!(names, vec!["DROPB", "DROPB-A", "DROPB-B"]);
+        // NET-162-125-128-0-1 has no children list, so its 404 is not a fetch
+        assert_eq!(result.fetches.len(), 3);
+    }
+
+    #[test]
+    fn get_uses_https() {
+        // The mock only knows the https URL, so a plain http request 404s
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[("https://whois.arin.net/rest/org/DROPB/nets",
+                                                                              DROPBOX_NETS)]),
+                                                       StdWhoisXmlParser::new());
+        let result = client.get("DROPB").unwrap();
+        assert!(result.fetches[0].url.starts_with("https://"));
+    }
+
+    #[test]
+    fn get_from_ripe() {
+        let nets = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<whois-resources>
+  <objects>
+    <object type="inetnum">
+      <attributes>
+        <attribute name="inetnum" value="193.0.0.0 - 193.0.7.255"/>
+        <attribute name="netname" value="RIPE-NCC"/>
+      </attributes>
+    </object>
+  </objects>
+</whois-resources>
+"#;
+        let url = Registry::Ripe.org_nets_url("ORG-RIEN1-RIPE");
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(&url, nets)]), RipeXmlParser::new())
+            .registry(Registry::Ripe);
+        let result = client.get("ORG-RIEN1-RIPE").unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "RIPE-NCC");
+        assert_eq!(result.ips[0].start_ip, ip("193.0.0.0"));
+        assert_eq!(result.ips[0].end_ip, ip("193.0.7.255"));
+        assert_eq!(WhoisSource::name(&client), "ripe");
+    }
+
+    #[test]
+    fn follows_referral_to_ripe() {
+        let arin_nets = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <orgRef handle="RIPE" name="RIPE Network Coordination Centre">https://whois.arin.net/rest/org/RIPE</orgRef>
+</nets>
+"#;
+        let ripe_nets = r#"<whois-resources><objects><object type="inetnum"><attributes>
+<attribute name="inetnum" value="193.0.0.0 - 193.0.7.255"/>
+<attribute name="netname" value="RIPE-NCC"/>
+</attributes></object></objects></whois-resources>"#;
+        let arin_url = Registry::Arin.org_nets_url("ORG-RIEN1-RIPE");
+        let arin = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(&arin_url, arin_nets)]),
+                                                     StdWhoisXmlParser::new());
+        let ripe_url = Registry::Ripe.org_nets_url("ORG-RIEN1-RIPE");
+        let ripe = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(&ripe_url, ripe_nets)]),
+                                                     RipeXmlParser::new())
+            .registry(Registry::Ripe);
+        let registries: Vec<(&str, &dyn WhoisSource)> = vec![("RIPE", &ripe)];
+        let max_depth = source::MAX_REFERRAL_DEPTH;
+        let result = source::lookup_following_referrals(&arin, "ORG-RIEN1-RIPE", &registries, max_depth).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "RIPE-NCC");
+        assert_eq!(result.ips[0].start_ip, ip("193.0.0.0"));
+        assert_eq!(result.ips[0].source, Some(String::from("ripe")));
+        assert_eq!(result.fetches.len(), 2);
+        assert_eq!(result.fetches[1].url, ripe_url);
+    }
+
+    #[test]
+    fn get_from_apnic() {
+        let entity = r#"{"objectClassName": "entity", "handle": "ORG-APNIC-AP",
+            "networks": [{"objectClassName": "ip network", "handle": "203.119.101.0 - 203.119.101.255",
+                          "startAddress": "203.119.101.0", "endAddress": "203.119.101.255",
+                          "ipVersion": "v4", "name": "APNIC-SERVICES"}]}"#;
+        let url = Registry::Apnic.org_nets_url("ORG-APNIC-AP");
+        assert_eq!(url, "https://rdap.apnic.net/entity/ORG-APNIC-AP");
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[(&url, entity)]), RdapJsonParser::new())
+            .registry(Registry::Apnic);
+        let result = client.get("ORG-APNIC-AP").unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "APNIC-SERVICES");
+        assert_eq!(result.ips[0].start_ip, ip("203.119.101.0"));
+        assert_eq!(result.ips[0].end_ip, ip("203.119.101.255"));
+        assert_eq!(result.ips[0].org, Some(String::from("ORG-APNIC-AP")));
+        assert_eq!(WhoisSource::name(&client), "apnic");
+    }
+
+    #[test]
+    fn get_ip() {
+        let net = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <registrationDate>2013-06-26T14:26:06-04:00</registrationDate>
+  <ref>https://whois.arin.net/rest/net/NET-162-125-0-0-1</ref>
+  <endAddress>162.125.255.255</endAddress>
+  <handle>NET-162-125-0-0-1</handle>
+  <name>DROPB</name>
+  <netBlocks>
+    <netBlock>
+      <cidrLength>16</cidrLength>
+      <endAddress>162.125.255.255</endAddress>
+      <description>Direct Allocation</description>
+      <type>DA</type>
+      <startAddress>162.125.0.0</startAddress>
+    </netBlock>
+  </netBlocks>
+  <orgRef handle="DROPB" name="Dropbox, Inc.">https://whois.arin.net/rest/org/DROPB</orgRef>
+  <parentNetRef handle="NET-162-0-0-0-0" name="NET162">https://whois.arin.net/rest/net/NET-162-0-0-0-0</parentNetRef>
+  <startAddress>162.125.0.0</startAddress>
+  <version>4</version>
+</net>
+"#;
+        let http_client = MockHttpClient::new(&[("https://whois.arin.net/rest/ip/162.125.1.1", net)]);
+        let client = WhoisCompanyIpsClient::from_parts(http_client, StdWhoisXmlParser::new());
+        let result = client.get_ip(ip("162.125.1.1")).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "DROPB");
+        assert_eq!(result.ips[0].handle, Some(String::from("NET-162-125-0-0-1")));
+        assert_eq!(result.ips[0].org, Some(String::from("DROPB")));
+        assert_eq!(result.ips[0].start_ip, ip("162.125.0.0"));
+        assert_eq!(result.ips[0].end_ip, ip("162.125.255.255"));
+        assert_eq!(result.ips[0].cidr_length, Some(16));
+        assert_eq!(result.fetches.len(), 1);
+
+        let error = client.get_ip(ip("192.0.2.1")).unwrap_err();
+        assert_eq!(error.kind(), WhoisErrorKind::NotFound);
+    }
+
+    #[test]
+    fn get_asn() {
+        let asn = r#"<?xml version="1.0"?>
+<asn xmlns="http://www.arin.net/whoisrws/core/v1">
+  <handle>AS19679</handle>
+  <name>DROPBOX</name>
+  <orgRef handle="DROPB" name="Dropbox, Inc.">https://whois.arin.net/rest/org/DROPB</orgRef>
+</asn>
+"#;
+        let http_client = MockHttpClient::new(&[("https://whois.arin.net/rest/asn/AS19679", asn),
+                                                ("https://whois.arin.net/rest/org/DROPB/nets", DROPBOX_NETS)]);
+        let client = WhoisCompanyIpsClient::from_parts(http_client, StdWhoisXmlParser::new());
+        assert_eq!(client.asn_org(19679).unwrap(), "DROPB");
+        let result = client.get_asn(19679).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "DROPB");
+        assert_eq!(result.ips[0].start_ip, ip("162.125.0.0"));
+
+        assert_eq!(client.get_asn(64496).unwrap_err().kind(), WhoisErrorKind::NotFound);
+    }
+
+    #[test]
+    fn pocs() {
+        let pocs = r#"<?xml version="1.0"?>
+<pocs xmlns="http://www.arin.net/whoisrws/core/v1">
+  <pocLinkRef description="Abuse" function="AB" handle="NETWO7047-ARIN">https://whois.arin.net/rest/poc/NETWO7047-ARIN</pocLinkRef>
+</pocs>
+"#;
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[("https://whois.arin.net/rest/org/DROPB/pocs",
+                                                                              pocs)]),
+                                                       StdWhoisXmlParser::new());
+        let pocs = client.pocs("DROPB").unwrap();
+        assert_eq!(pocs.len(), 1);
+        assert_eq!(pocs[0].handle, "NETWO7047-ARIN");
+        assert_eq!(pocs[0].role, "Abuse");
+        assert!(client.pocs("NOSUCHORG").is_err());
+    }
+
+    #[test]
+    fn resolve_abuse_contacts() {
+        let pocs = r#"<?xml version="1.0"?>
+<pocs xmlns="http://www.arin.net/whoisrws/core/v1">
+  <pocLinkRef description="Admin" function="AD" handle="DROPB1-ARIN">https://whois.arin.net/rest/poc/DROPB1-ARIN</pocLinkRef>
+  <pocLinkRef description="Abuse" function="AB" handle="NETWO7047-ARIN">https://whois.arin.net/rest/poc/NETWO7047-ARIN</pocLinkRef>
+</pocs>
+"#;
+        let poc = r#"<?xml version="1.0"?>
+<poc xmlns="http://www.arin.net/whoisrws/core/v1">
+  <emails><email>abuse@dropbox.com</email></emails>
+  <handle>NETWO7047-ARIN</handle>
+</poc>
+"#;
+        let responses = [("https://whois.arin.net/rest/org/DROPB/pocs", pocs),
+                         ("https://whois.arin.net/rest/poc/NETWO7047-ARIN", poc)];
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&responses), StdWhoisXmlParser::new());
+        let mut result = WhoisResult::new(vec![dropbox(),
+                                               range("DROPBOX-V6", "2620:100:6000::", "2620:100:6fff::"),
+                                               range("UNRESOLVED", "192.0.2.0", "192.0.2.255")]);
+        result.ips[0].org = Some(String::from("DROPB"));
+        result.ips[1].org = Some(String::from("DROPB"));
+        client.resolve_abuse_contacts(&mut result).unwrap();
+
+        let emails: Vec<Option<&str>> = result.ips
+            .iter()
+            .map(|ip| ip.abuse_email.as_ref().map(|email| email.as_ref()))
+            .collect();
+        assert_eq!(emails, vec![Some("abuse@dropbox.com"), Some("abuse@dropbox.com"), None]);
+        assert_eq!(*client.client.requested.borrow(),
+                   vec![String::from("https://whois.arin.net/rest/org/DROPB/pocs"),
+                        String::from("https://whois.arin.net/rest/poc/NETWO7047-ARIN")]);
+    }
+
+    #[test]
+    fn health_check() {
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[("https://whois.arin.net/rest/org/ARIN",
+                                                                              "<org/>")]),
+                                                       StdWhoisXmlParser::new());
+        assert!(client.health_check().is_ok());
+
+        let client = WhoisCompanyIpsClient::from_parts(MockHttpClient::new(&[]), StdWhoisXmlParser::new());
+        match client.health_check() {
+            Err(WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound))) => {}
+            other => panic!("Expected an HTTP error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn serialize_as_ranges() {
+        let result = WhoisResult::new(vec![dropbox(), range("TEST6", "2001:db8::", "2001:db8::ff")]);
+        assert_eq!(serde_json::to_string(&result).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\"},\
+                    {\"name\":\"TEST6\",\"start_ip\":\"2001:db8::\",\"end_ip\":\"2001:db8::ff\"}]");
+        assert_eq!(serde_json::to_string(&WhoisResult::new(Vec::new())).unwrap(), "[]");
+    }
+
+    #[test]
+    fn sorted() {
+        let result = WhoisResult::new(vec![range("V6-B", "2001:db8::1:0", "2001:db8::1:ff"),
+                                           range("V4-B", "10.0.0.0", "10.0.0.255"),
+                                           range("V6-A", "2001:db8::a", "2001:db8::ff"),
+                                           range("V4-WIDE", "9.0.0.0", "9.255.255.255"),
+                                           range("V4-A", "9.0.0.0", "9.0.0.255")]);
+        let names: Vec<String> = result.sorted().ips.into_iter().map(|ip| ip.name).collect();
+        assert_eq!(names, vec!["V4-A", "V4-WIDE", "V4-B", "V6-A", "V6-B"]);
+        // The original is left as it was
+        assert_eq!(result.ips[0].name, "V6-B");
+    }
+
+    #[test]
+    fn total_address_count() {
+        assert_eq!(WhoisResult::new(vec![range("TEST", "192.0.2.0", "192.0.2.255")]).total_address_count(), 256);
+        let result = WhoisResult::new(vec![range("TEST", "192.0.2.0", "192.0.2.255"),
+                                           range("TEST6", "2001:db8::", "2001:db8::f")]);
+        assert_eq!(result.total_address_count(), 256 + 16);
+        assert_eq!(WhoisResult::new(Vec::new()).total_address_count(), 0);
+    }
+
+    #[test]
+    fn total_address_count_saturates() {
+        // All of IPv6 is one more address than a u128 can count
+        let result = WhoisResult::new(vec![range("ALL6", "::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"),
+                                           range("TEST", "192.0.2.0", "192.0.2.255")]);
+        assert_eq!(result.total_address_count(), u128::max_value());
+    }
+
+    #[test]
+    fn cidr_count() {
+        let result = WhoisResult::new(vec![dropbox(),
+                                           range("TEST", "192.0.2.1", "192.0.2.10"),
+                                           range("TEST6", "2001:db8::1", "2001:db8::4")]);
+        assert_eq!(result.cidr_count(), 1 + 5 + 3);
+        assert_eq!(WhoisResult::new(Vec::new()).cidr_count(), 0);
+    }
+
+    #[test]
+    fn complement_within() {
+        let result = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255"),
+                                           range("B", "10.0.2.0", "10.0.3.255")]);
+        let gaps: Vec<String> = result.complement_within(IpNet::from_str("10.0.0.0/22").unwrap())
+            .iter()
+            .map(|net| net.to_string())
+            .collect();
+        assert_eq!(gaps, vec!["10.0.1.0/24"]);
+        assert!(result.complement_within(IpNet::from_str("10.0.2.0/23").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn retain_family() {
+        let mixed = || {
+            WhoisResult::new(vec![range("TEST6", "2001:db8::", "2001:db8::ff"),
+                                  range("TEST", "192.0.2.0", "192.0.2.255"),
+                                  range("DROPB", "162.125.0.0", "162.125.255.255")])
+        };
+        let names = |result: &WhoisResult| -> Vec<String> { result.ips.iter().map(|ip| ip.name.clone()).collect() };
+
+        let mut result = mixed();
+        result.retain_family(Family::V4);
+        assert_eq!(names(&result), vec!["TEST", "DROPB"]);
+
+        let mut result = mixed();
+        result.retain_family(Family::V6);
+        assert_eq!(names(&result), vec!["TEST6"]);
+
+        // Unlike retain_family_if_both, a missing family leaves nothing
+        let mut result = WhoisResult::new(vec![range("TEST6", "2001:db8::", "2001:db8::ff")]);
+        result.retain_family(Family::V4);
+        assert!(result.ips.is_empty());
+    }
+
+    #[test]
+    fn retain_family_if_both() {
+        let mut result = WhoisResult::new(vec![range("TEST6", "2001:db8::", "2001:db8::ff"),
+                                               range("TEST", "192.0.2.0", "192.0.2.255")]);
+        result.retain_family_if_both(Family::V4);
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "TEST");
+
+        let mut result = WhoisResult::new(vec![range("TEST6", "2001:db8::", "2001:db8::ff")]);
+        result.retain_family_if_both(Family::V4);
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "TEST6");
+
+        let mut result = WhoisResult::new(Vec::new());
+        result.retain_family_if_both(Family::V4);
+        assert!(result.ips.is_empty());
+    }
+
+    #[test]
+    fn content_hash() {
+        let result = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255"),
+                                           range("B", "192.0.2.0", "192.0.2.255")]);
+        let reordered = WhoisResult::new(vec![range("B", "192.0.2.0", "192.0.2.255"),
+                                              range("A", "10.0.0.0", "10.0.0.255"),
+                                              range("A", "10.0.0.0", "10.0.0.255")]);
+        assert_eq!(result.content_hash(), reordered.content_hash());
+        assert_eq!(result.content_hash().len(), 64);
+
+        let added = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255"),
+                                          range("B", "192.0.2.0", "192.0.2.255"),
+                                          range("C", "198.51.100.0", "198.51.100.255")]);
+        assert!(result.content_hash() != added.content_hash());
+    }
+
+    fn bounds_of(ips: &[WhoisIpResult]) -> Vec<(IpAddr, IpAddr)> {
+        ips.iter().map(|ip| (ip.start_ip, ip.end_ip)).collect()
+    }
+
+    #[test]
+    fn diff_added_and_removed() {
+        let earlier = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255"),
+                                            range("B", "192.0.2.0", "192.0.2.255")]);
+        let result = WhoisResult::new(vec![range("B", "192.0.2.0", "192.0.2.255"),
+                                           range("C", "198.51.100.0", "198.51.100.255"),
+                                           range("C", "198.51.100.0", "198.51.100.255")]);
+        let diff = result.diff(&earlier);
+        assert_eq!(bounds_of(&diff.added), vec![(ip("198.51.100.0"), ip("198.51.100.255"))]);
+        assert_eq!(bounds_of(&diff.removed), vec![(ip("10.0.0.0"), ip("10.0.0.255"))]);
+        assert_eq!(bounds_of(&diff.unchanged), vec![(ip("192.0.2.0"), ip("192.0.2.255"))]);
+    }
+
+    #[test]
+    fn diff_unchanged() {
+        let earlier = WhoisResult::new(vec![range("A", "10.0.0.0", "10.0.0.255")]);
+        // Only the bounds count, not the name
+        let result = WhoisResult::new(vec![range("A-RENAMED", "10.0.0.0", "10.0.0.255")]);
+        let diff = result.diff(&earlier);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.unchanged[0].name, "A-RENAMED");
+
+        let diff = WhoisResult::new(Vec::new()).diff(&WhoisResult::new(Vec::new()));
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.unchanged.is_empty());
+    }
+
+    #[test]
+    fn aggregate_cidrs() {
+        let mut result = WhoisResult::new(vec![range("SECOND", "10.0.1.0", "10.0.1.255"),
+                                               range("FIRST", "10.0.0.0", "10.0.0.255"),
+                                               range("APART", "10.0.3.0", "10.0.3.255")]);
+        result.aggregate_cidrs();
+        let ips: Vec<(String, IpAddr, IpAddr)> = result.ips
+            .iter()
+            .map(|ip| (ip.name.clone(), ip.start_ip, ip.end_ip))
+            .collect();
+        assert_eq!(ips,
+                   vec![(String::from("FIRST"), ip("10.0.0.0"), ip("10.0.1.255")),
+                        (String::from("APART"), ip("10.0.3.0"), ip("10.0.3.255"))]);
+    }
+
+    #[test]
+    fn split_at_start() {
+        let (below, above) = dropbox().split_at(ip("162.125.0.0"));
+        assert_eq!(bounds(below), None);
+        assert_eq!(bounds(above), Some((ip("162.125.0.0"), ip("162.125.255.255"))));
+    }
+
+    #[test]
+    fn split_at_middle() {
+        let (below, above) = dropbox().split_at(ip("162.125.128.0"));
+        assert_eq!(bounds(below), Some((ip("162.125.0.0"), ip("162.125.127.255"))));
+        assert_eq!(bounds(above), Some((ip("162.125.128.0"), ip("162.125.255.255"))));
+    }
+
+    #[test]
+    fn split_at_end() {
+        let (below, above) = dropbox().split_at(ip("162.125.255.255"));
+        assert_eq!(bounds(below), Some((ip("162.125.0.0"), ip("162.125.255.254"))));
+        assert_eq!(bounds(above), Some((ip("162.125.255.255"), ip("162.125.255.255"))));
+    }
+
+    #[test]
+    fn split_at_outside() {
+        let (below, above) = dropbox().split_at(ip("10.0.0.0"));
+        assert_eq!(bounds(below), None);
+        assert_eq!(bounds(above), Some((ip("162.125.0.0"), ip("162.125.255.255"))));
+
+        let (below, above) = dropbox().split_at(ip("192.168.0.0"));
+        assert_eq!(bounds(below), Some((ip("162.125.0.0"), ip("162.125.255.255"))));
+        assert_eq!(bounds(above), None);
+    }
+
+    #[test]
+    fn family() {
+        assert_eq!(dropbox().family(), Family::V4);
+        assert_eq!(range("DROPBOX-V6", "2620:100:6000::", "2620:100:6000:ffff:ffff:ffff:ffff:ffff").family(),
+                   Family::V6);
+    }
+
+    #[test]
+    fn split_at_other_family() {
+        let (below, above) = dropbox().split_at(ip("2620:100::"));
+        assert_eq!(bounds(below), Some((ip("162.125.0.0"), ip("162.125.255.255"))));
+        assert_eq!(bounds(above), None);
+    }
+
+    #[test]
+    fn prefix_len() {
+        assert_eq!(dropbox().prefix_len(), Some(16));
+        let mut unaligned = WhoisIpResult::new(String::from("UNALIGNED"), ip("192.0.2.0"), ip("192.0.2.2"));
+        assert_eq!(unaligned.prefix_len(), None);
+        unaligned.cidr_length = Some(24);
+        assert_eq!(unaligned.prefix_len(), Some(24));
+    }
+}
@@ -3,96 +3,799 @@
 
 #[macro_use]
 extern crate clap;
-extern crate iprange;
-extern crate hyper;
-extern crate xml;
+extern crate env_logger;
+extern crate log;
+extern crate whois_ips;
 
 use std::result::Result::{self, Ok};
+use std::str::FromStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
 use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::{Arg, App};
+use log::LevelFilter;
 
-use iprange::IpAddrRange;
+use whois_ips::{batch, demo, hierarchy, output, source};
+use whois_ips::{WhoisCompanyIpsClient, WhoisResult, WhoisIpResult, Transforms, MAX_CHILD_DEPTH};
+use whois_ips::{WhoisError, StdWhoisHttpClient, WhoisXmlParser, StdWhoisXmlParser, WhoisParser, JsonWhoisParser,
+                BulkFileParser, Registry, WhoisSource};
+use whois_ips::cidr::IpNet;
+use whois_ips::cache::CachingWhoisHttpClient;
+use whois_ips::inflight::{self, InflightLimitedWhoisHttpClient, InflightLimit};
+use whois_ips::output::{CountFormat, CsvDialect, CsvQuote, Field, OutputFormat, OutputOptions, Explain};
+use whois_ips::ranges::{DedupKey, Family, SortOrder};
+use whois_ips::retry::{ExponentialBackoff, RetryingWhoisHttpClient};
 
-mod httpclient;
-use httpclient::{WhoisHttpClient, StdWhoisHttpClient};
-
-mod xmlparser;
-use xmlparser::{WhoisXmlParser, StdWhoisXmlParser};
-
-
-#[derive(Debug)]
-pub struct WhoisIpResult {
-    name: String,
-    start_ip: IpAddr,
-    end_ip: IpAddr,
-}
-
-#[derive(Debug)]
-pub struct WhoisResult {
-    ips: Vec<WhoisIpResult>,
+/// Write a failed lookup's error to stdout, or to stderr for `--error-output stderr`.
+fn report_error(err: &WhoisError, handle: Option<&str>, format: OutputFormat, to_stderr: bool) {
+    let written = if to_stderr {
+        output::write_error(err, handle, format, &mut io::stderr())
+    } else {
+        output::write_error(err, handle, format, &mut io::stdout())
+    };
+    written.unwrap();
 }
 
-impl WhoisResult {
-    fn new(ips: Vec<WhoisIpResult>) -> WhoisResult {
-        WhoisResult { ips: ips }
+/// The level `-v` given `verbosity` times logs at, or `None` to leave it to
+/// `RUST_LOG`.
+fn log_level(verbosity: u64) -> Option<LevelFilter> {
+    match verbosity {
+        0 => None,
+        1 => Some(LevelFilter::Info),
+        2 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
     }
 }
 
+/// Exit code for `--fail-on-empty` when every lookup succeeded but found no
+/// ranges, distinct from the 1 of a failed lookup.
+const EXIT_EMPTY: i32 = 2;
 
-struct WhoisCompanyIpsClient<C: WhoisHttpClient, P: WhoisXmlParser> {
-    client: C,
-    parser: P,
+/// The message for `--fail-on-empty`, if the lookups of `handles` found
+/// `found` ranges in all.
+fn empty_result_message(handles: &[String], found: usize) -> Option<String> {
+    if found > 0 {
+        return None;
+    }
+    Some(format!("No ranges found for {}", handles.join(", ")))
 }
 
-impl WhoisCompanyIpsClient<StdWhoisHttpClient, StdWhoisXmlParser> {
-    fn new() -> WhoisCompanyIpsClient<StdWhoisHttpClient, StdWhoisXmlParser> {
-        WhoisCompanyIpsClient {
-            client: StdWhoisHttpClient::new(),
-            parser: StdWhoisXmlParser::new(),
-        }
+/// Read a file of CIDR prefixes, one per line, as a result with a range per
+/// prefix. Blank lines and `#` comments are skipped.
+fn read_prefixes(path: &str) -> Result<WhoisResult, String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut ips = Vec::new();
+    for line in content.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let net = IpNet::from_str(line)?;
+        ips.push(WhoisIpResult::new(line.to_string(), net.addr(), net.last_addr()));
     }
+    Ok(WhoisResult::new(ips))
+}
 
-    fn get(&self, company: &str) -> Result<WhoisResult, String> {
-        let url = format!("http://whois.arin.net/rest/org/{}/nets", company);
-        let http_response = self.client.get_content(&url);
-        if http_response.is_err() {
-            return Err(format!("HTTP Error: {:}", http_response.unwrap_err()));
-        }
-        let parsed_response = self.parser.parse_content(http_response.unwrap());
-        if parsed_response.is_err() {
-            return Err(format!("XML Error: {:}", parsed_response.unwrap_err()));
-        }
-        Ok(parsed_response.unwrap())
-    }
+/// The org or POC handles in a `--input` file, one per line. Blank lines
+/// and `#` comments are skipped.
+fn parse_handles(content: &str) -> Vec<String> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn read_handles<R: Read>(mut reader: R) -> io::Result<Vec<String>> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    Ok(parse_handles(&content))
 }
 
-// https://www.arin.net/resources/whoisrws/whois_api.html
 fn main() {
     let cmd_line_args = App::new("myapp")
         .version(crate_version!())
         .author(crate_authors!())
         .about("Look up assigned IPv4/6 address ranges by company name")
-        .arg(Arg::with_name("COMPANY").required(true).takes_value(true))
+        .arg(Arg::with_name("COMPANY")
+            .required_unless_one(&["input", "stdin", "input-url", "ip", "asn", "demo", "health-check"])
+            .conflicts_with_all(&["input", "stdin", "input-url", "ip", "asn", "demo", "health-check"])
+            .takes_value(true)
+            .multiple(true))
+        .arg(Arg::with_name("input")
+            .long("input")
+            .value_name("FILE")
+            .help("Look up the handles in FILE, one per line, as if each was given as COMPANY, or in stdin for -")
+            .conflicts_with_all(&["input-url", "ip", "asn", "demo", "health-check"])
+            .takes_value(true))
+        .arg(Arg::with_name("stdin")
+            .long("stdin")
+            .help("Look up the handles in stdin, the same as --input -")
+            .conflicts_with_all(&["input", "input-url", "ip", "asn", "demo", "health-check"]))
+        .arg(Arg::with_name("input-url")
+            .long("input-url")
+            .value_name("URL")
+            .help("Fetch and parse this ARIN REST URL instead of looking up a company")
+            .takes_value(true))
+        .arg(Arg::with_name("ip")
+            .short("i")
+            .long("ip")
+            .value_name("ADDR")
+            .help("Look up the net an IP address belongs to instead of a company's nets")
+            .conflicts_with_all(&["input-url", "demo", "bulk-file", "follow-referral", "health-check", "list-pocs",
+                                  "registry"])
+            .takes_value(true))
+        .arg(Arg::with_name("asn")
+            .short("a")
+            .long("asn")
+            .value_name("ASN")
+            .help("Look up the nets of the org an AS number, e.g. 19679, AS19679 or as19679, is registered to")
+            .validator(|s| StdWhoisXmlParser::parse_asn_arg(&s).map(|_| ()))
+            .conflicts_with_all(&["input-url", "ip", "demo", "bulk-file", "follow-referral", "health-check",
+                                  "list-pocs", "registry"])
+            .takes_value(true))
+        .arg(Arg::with_name("bulk-file")
+            .long("bulk-file")
+            .value_name("FILE")
+            .conflicts_with_all(&["input-url", "demo", "follow-referral"])
+            .help("Find the company's nets in this ARIN bulk Whois flat file instead of asking the API")
+            .takes_value(true))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .help("Output format for results and errors")
+            .possible_values(&["plain", "json", "ndjson", "json-stream", "summary", "oneline", "zmap", "tf", "cisco",
+                               "nmap", "ipset", "csv"])
+            .default_value("plain"))
+        .arg(Arg::with_name("csv-delimiter")
+            .long("csv-delimiter")
+            .help("Field delimiter for --format csv, a single character or \\t for a tab")
+            .validator(|s| CsvDialect::parse_delimiter(&s).map(|_| ()))
+            .default_value(","))
+        .arg(Arg::with_name("csv-quote")
+            .long("csv-quote")
+            .help("Which fields --format csv puts in double quotes")
+            .possible_values(&["always", "minimal"])
+            .default_value("minimal"))
+        .arg(Arg::with_name("ipset-name")
+            .long("ipset-name")
+            .value_name("SET")
+            .help("The set --format ipset adds IPv4 prefixes to, and with -v6 appended IPv6 prefixes")
+            .validator(|s| output::parse_ipset_name(&s).map(|_| ()))
+            .default_value(output::DEFAULT_IPSET_NAME))
+        .arg(Arg::with_name("fields")
+            .long("fields")
+            .help("Extra per-range fields for --format json")
+            .possible_values(&["cidr"])
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true))
+        .arg(Arg::with_name("include-bogons")
+            .long("include-bogons")
+            .help("Keep private and reserved space in --format zmap output"))
+        .arg(Arg::with_name("error-output")
+            .long("error-output")
+            .help("Where to write a failed lookup's error")
+            .possible_values(&["stdout", "stderr"])
+            .default_value("stdout"))
+        .arg(Arg::with_name("output")
+            .short("O")
+            .long("output")
+            .help("Write the results to a file instead of stdout")
+            .value_name("PATH")
+            .takes_value(true))
+        .arg(Arg::with_name("strict")
+            .long("strict")
+            .help("Treat inconsistencies in the ARIN response as errors"))
+        .arg(Arg::with_name("allow-partial")
+            .long("allow-partial")
+            .help("Print the ranges ARIN returned when it hits its result limit, with a warning"))
+        .arg(Arg::with_name("fail-on-empty")
+            .long("fail-on-empty")
+            .help("Exit with status 2 if the lookups succeed but find no ranges"))
+        .arg(Arg::with_name("multi-document")
+            .long("multi-document")
+            .help("Accept responses made of several XML documents one after another"))
+        .arg(Arg::with_name("api-format")
+            .long("api-format")
+            .help("Format to ask the ARIN API to answer in")
+            .possible_values(&["xml", "json"])
+            .default_value("xml"))
+        .arg(Arg::with_name("registry")
+            .long("registry")
+            .help("Registry to look companies up in, ARIN unless given")
+            .possible_values(&["arin", "ripe", "apnic", "lacnic", "afrinic"])
+            .conflicts_with_all(&["bulk-file", "follow-referral", "resolve-details", "with-abuse-contact",
+                                  "include-children", "org-hierarchy", "list-pocs", "health-check"])
+            .takes_value(true))
+        .arg(Arg::with_name("follow-referral")
+            .long("follow-referral")
+            .help("Re-query the registry ARIN refers the lookup to"))
+        .arg(Arg::with_name("resolve-details")
+            .long("resolve-details")
+            .help("Fetch each net's own record for details such as its origin ASNs and comments"))
+        .arg(Arg::with_name("with-abuse-contact")
+            .long("with-abuse-contact")
+            .help("Add the email of each net's abuse contact to json and csv output, implies --resolve-details"))
+        .arg(Arg::with_name("include-children")
+            .long("include-children")
+            .help("Also print the nets reassigned from each net, a few levels deep"))
+        .arg(Arg::with_name("org-hierarchy")
+            .long("org-hierarchy")
+            .help("Print each org's nets and reassignments as an indented tree, implies --include-children \
+                   and --resolve-details"))
+        .arg(Arg::with_name("retries")
+            .long("retries")
+            .value_name("N")
+            .help("Retry a request up to N times while ARIN is unavailable")
+            .default_value("0"))
+        .arg(Arg::with_name("cache-dir")
+            .long("cache-dir")
+            .value_name("DIR")
+            .help("Keep ARIN's responses in DIR and answer repeated requests from there")
+            .takes_value(true))
+        .arg(Arg::with_name("cache-ttl")
+            .long("cache-ttl")
+            .value_name("SECONDS")
+            .help("How long a response kept by --cache-dir is used for")
+            .default_value("3600"))
+        .arg(Arg::with_name("max-response-bytes")
+            .long("max-response-bytes")
+            .value_name("N")
+            .help("Fail a request whose response is longer than N bytes")
+            .takes_value(true))
+        .arg(Arg::with_name("max-inflight")
+            .long("max-inflight")
+            .value_name("N")
+            .help("Never have more than N requests to ARIN open at once")
+            .validator(|s| inflight::parse_max_inflight(&s).map(|_| ()))
+            .takes_value(true))
+        .arg(Arg::with_name("timeout-per-handle")
+            .long("timeout-per-handle")
+            .value_name("SECONDS")
+            .help("Give up on a handle whose lookup takes longer than this")
+            .takes_value(true))
+        .arg(Arg::with_name("jobs")
+            .long("jobs")
+            .short("j")
+            .value_name("N")
+            .help("Look up to N handles at once")
+            .default_value("1"))
+        .arg(Arg::with_name("ipv4-only")
+            .long("ipv4-only")
+            .conflicts_with_all(&["ipv6-only", "prefer-family"])
+            .help("Keep only the IPv4 ranges"))
+        .arg(Arg::with_name("ipv6-only")
+            .long("ipv6-only")
+            .conflicts_with_all(&["ipv4-only", "prefer-family"])
+            .help("Keep only the IPv6 ranges"))
+        .arg(Arg::with_name("prefer-family")
+            .long("prefer-family")
+            .help("Keep only the ranges of this family, unless there are none")
+            .possible_values(&["v4", "v6"])
+            .takes_value(true))
+        .arg(Arg::with_name("dedup")
+            .long("dedup")
+            .help("Drop ranges with the same start and end as an earlier one"))
+        .arg(Arg::with_name("dedup-by")
+            .long("dedup-by")
+            .help("What makes ranges duplicates, falling back to range for nets without a handle, implies --dedup")
+            .possible_values(&["range", "handle"])
+            .takes_value(true))
+        .arg(Arg::with_name("rir-precedence")
+            .long("rir-precedence")
+            .value_name("REGISTRIES")
+            .help("When deduplicating by range, keep the entry from the registry listed first, e.g. arin,ripe,apnic")
+            .use_delimiter(true)
+            .takes_value(true))
+        .arg(Arg::with_name("merge")
+            .long("merge")
+            .help("Combine overlapping and adjacent ranges"))
+        .arg(Arg::with_name("normalize")
+            .long("normalize")
+            .help("Shorthand for --dedup --sort-by start --merge"))
+        .arg(Arg::with_name("aggregate")
+            .long("aggregate")
+            .help("Combine ranges into the fewest CIDR prefixes covering the same addresses"))
+        .arg(Arg::with_name("sort-by")
+            .long("sort-by")
+            .help("Sort ranges by start address or by size, largest first")
+            .possible_values(&["start", "size"])
+            .takes_value(true))
+        .arg(Arg::with_name("sort")
+            .long("sort")
+            .conflicts_with("sort-by")
+            .help("Sort ranges by start address, the same as --sort-by start"))
+        .arg(Arg::with_name("sample")
+            .long("sample")
+            .value_name("N")
+            .help("Print N ranges spread evenly across the sorted result")
+            .takes_value(true))
+        .arg(Arg::with_name("ascii-names")
+            .long("ascii-names")
+            .help("Escape non-ASCII characters in net names as \\u{...}"))
+        .arg(Arg::with_name("complement-within")
+            .long("complement-within")
+            .value_name("CIDR")
+            .help("Print the prefixes inside CIDR that no range covers, instead of the ranges")
+            .takes_value(true))
+        .arg(Arg::with_name("hash")
+            .long("hash")
+            .conflicts_with("complement-within")
+            .help("Print a SHA-256 of the distinct ranges instead of the ranges, to detect changes"))
+        .arg(Arg::with_name("count")
+            .long("count")
+            .conflicts_with_all(&["complement-within", "hash"])
+            .help("Print the total number of addresses the ranges cover instead of the ranges"))
+        .arg(Arg::with_name("diff-against")
+            .long("diff-against")
+            .value_name("FILE")
+            .conflicts_with_all(&["complement-within", "hash", "count"])
+            .help("Print the CIDR prefixes added (+) and removed (-) since FILE, a list of prefixes one per line")
+            .takes_value(true))
+        .arg(Arg::with_name("relative-to")
+            .long("relative-to")
+            .value_name("CIDR")
+            .conflicts_with_all(&["complement-within", "hash", "count", "diff-against"])
+            .help("Print each range as its offset and length within CIDR, warning about ranges outside it")
+            .takes_value(true))
+        .arg(Arg::with_name("danger-insecure")
+            .long("danger-insecure")
+            .hidden(true)
+            .help("Accept invalid TLS certificates, for testing against local servers only"))
+        .arg(Arg::with_name("health-check")
+            .long("health-check")
+            .conflicts_with_all(&["input-url", "demo"])
+            .help("Only check that ARIN can be reached, exiting non-zero if not"))
+        .arg(Arg::with_name("list-pocs")
+            .long("list-pocs")
+            .conflicts_with_all(&["input-url", "demo", "bulk-file", "health-check"])
+            .help("Print the points of contact of each org, such as its abuse contact, instead of its ranges"))
+        .arg(Arg::with_name("demo")
+            .long("demo")
+            .hidden(true)
+            .conflicts_with("input-url")
+            .help("Print made-up ranges instead of looking anything up, to show the output formats"))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .hidden(true)
+            .help("Vary the made-up ranges printed by --demo")
+            .default_value("0"))
+        .arg(Arg::with_name("trace-http")
+            .long("trace-http")
+            .help("Write every request and its full response to stderr"))
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .multiple(true)
+            .help("Log requests and their results to stderr, -vv for more detail"))
+        .arg(Arg::with_name("explain")
+            .long("explain")
+            .help("Describe each request and transformation on stderr"))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("Print the number of ranges and addresses to stderr"))
+        .arg(Arg::with_name("count-format")
+            .long("count-format")
+            .help("How address counts are printed")
+            .possible_values(&["raw", "grouped"])
+            .default_value("raw"))
         .get_matches_safe()
         .unwrap_or_else(|e| e.exit());
 
-    let company_name = cmd_line_args.value_of("COMPANY").unwrap();
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = log_level(cmd_line_args.occurrences_of("verbose")) {
+        logger.filter(None, level);
+    }
+    logger.init();
+
+    let input_url = cmd_line_args.value_of("input-url").map(String::from);
+    let demo = cmd_line_args.is_present("demo");
+    let lookup_ip = if cmd_line_args.is_present("ip") {
+        Some(value_t!(cmd_line_args, "ip", IpAddr).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    let lookup_asn = cmd_line_args.value_of("asn").map(|asn| StdWhoisXmlParser::parse_asn_arg(asn).unwrap());
+    let handles: Vec<String> = match input_url {
+        Some(ref url) => vec![url.clone()],
+        None if lookup_ip.is_some() => vec![lookup_ip.unwrap().to_string()],
+        None if lookup_asn.is_some() => vec![format!("AS{}", lookup_asn.unwrap())],
+        None if demo => vec![String::from("DEMO")],
+        None => {
+            let input = if cmd_line_args.is_present("stdin") { Some("-") } else { cmd_line_args.value_of("input") };
+            match input {
+                Some("-") => {
+                    read_handles(io::stdin()).unwrap_or_else(|e| {
+                        eprintln!("Failed to read stdin: {}", e);
+                        process::exit(1);
+                    })
+                }
+                Some(path) => {
+                    File::open(path).and_then(read_handles).unwrap_or_else(|e| {
+                        eprintln!("Failed to read {}: {}", path, e);
+                        process::exit(1);
+                    })
+                }
+                None => cmd_line_args.values_of("COMPANY").unwrap().map(String::from).collect(),
+            }
+        }
+    };
+    if handles.is_empty() {
+        eprintln!("No handles to look up");
+        process::exit(1);
+    }
+    let count_format = CountFormat::from_str(cmd_line_args.value_of("count-format").unwrap()).unwrap();
+    let output_format = match OutputFormat::from_str(cmd_line_args.value_of("format").unwrap()).unwrap() {
+        OutputFormat::Csv(_) => {
+            OutputFormat::Csv(CsvDialect {
+                delimiter: CsvDialect::parse_delimiter(cmd_line_args.value_of("csv-delimiter").unwrap()).unwrap(),
+                quote: CsvQuote::from_str(cmd_line_args.value_of("csv-quote").unwrap()).unwrap(),
+            })
+        }
+        output_format => output_format,
+    };
+    let to_stderr = cmd_line_args.value_of("error-output") == Some("stderr");
+    let complement_within = if cmd_line_args.is_present("complement-within") {
+        Some(value_t!(cmd_line_args, "complement-within", IpNet).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    let relative_to = if cmd_line_args.is_present("relative-to") {
+        Some(value_t!(cmd_line_args, "relative-to", IpNet).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    let mut output_options = OutputOptions::default();
+    if let Some(fields) = cmd_line_args.values_of("fields") {
+        output_options.fields = fields.map(|field| Field::from_str(field).unwrap()).collect();
+    }
+    output_options.include_bogons = cmd_line_args.is_present("include-bogons");
+    output_options.ipset_name = cmd_line_args.value_of("ipset-name").map(String::from);
 
-    let client = WhoisCompanyIpsClient::new();
-    let response = client.get(company_name);
+    let retries = value_t!(cmd_line_args, "retries", u32).unwrap_or_else(|e| e.exit());
+    let max_response_bytes = if cmd_line_args.is_present("max-response-bytes") {
+        Some(value_t!(cmd_line_args, "max-response-bytes", u64).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    let strict = cmd_line_args.is_present("strict");
+    let allow_partial = cmd_line_args.is_present("allow-partial");
+    let registry = cmd_line_args.value_of("registry")
+        .map(|registry| Registry::from_str(registry).unwrap())
+        .unwrap_or_default();
+    let parser = match cmd_line_args.value_of("api-format") {
+        _ if registry != Registry::Arin => WhoisParser::for_registry(registry),
+        Some("json") => WhoisParser::Json(JsonWhoisParser::new().strict(strict).allow_partial(allow_partial)),
+        _ => {
+            WhoisParser::Xml(StdWhoisXmlParser::new()
+                .strict(strict)
+                .allow_partial(allow_partial)
+                .multi_document(cmd_line_args.is_present("multi-document")))
+        }
+    };
+    let cache_dir = cmd_line_args.value_of("cache-dir").map(PathBuf::from);
+    // One limit for every registry's client, so --follow-referral does not
+    // multiply --max-inflight
+    let inflight_limit = cmd_line_args.value_of("max-inflight")
+        .map(|s| Arc::new(InflightLimit::new(inflight::parse_max_inflight(s).unwrap())));
+    let cache_ttl = Duration::from_secs(value_t!(cmd_line_args, "cache-ttl", u64).unwrap_or_else(|e| e.exit()));
+    if cmd_line_args.is_present("danger-insecure") {
+        eprintln!("Warning: --danger-insecure turns off TLS certificate verification");
+    }
+    let client_for = |registry: Registry, parser: WhoisParser| {
+        let http_client = StdWhoisHttpClient::new()
+            .with_product_info("whois-ips", crate_version!())
+            .accept(parser.accept())
+            .danger_accept_invalid_certs(cmd_line_args.is_present("danger-insecure"))
+            .trace_http(cmd_line_args.is_present("trace-http"))
+            .max_response_bytes(max_response_bytes);
+        // Limit below the retries so a request waiting to be retried does
+        // not hold on to its place
+        let http_client = InflightLimitedWhoisHttpClient::with_limit(http_client, inflight_limit.clone());
+        let http_client = RetryingWhoisHttpClient::new(http_client, ExponentialBackoff::new(retries));
+        let http_client = CachingWhoisHttpClient::new(http_client, cache_dir.clone(), cache_ttl);
+        WhoisCompanyIpsClient::from_parts(http_client, parser).registry(registry)
+    };
+    let client = client_for(registry, parser);
+    if cmd_line_args.is_present("health-check") {
+        if let Err(err) = client.health_check() {
+            report_error(&err, None, output_format, to_stderr);
+            process::exit(1);
+        }
+        return;
+    }
+    let mut out: Box<dyn Write> = match cmd_line_args.value_of("output") {
+        Some(path) => {
+            Box::new(File::create(path).unwrap_or_else(|e| {
+                eprintln!("Failed to create {}: {}", path, e);
+                process::exit(1);
+            }))
+        }
+        None => Box::new(io::stdout()),
+    };
+    if cmd_line_args.is_present("list-pocs") {
+        let mut failed = false;
+        for handle in &handles {
+            match client.pocs(handle) {
+                Ok(pocs) => output::write_pocs(handle, &pocs, output_format, &mut out).unwrap(),
+                Err(err) => {
+                    failed = true;
+                    let handle = if handles.len() > 1 { Some(handle.as_ref()) } else { None };
+                    report_error(&err, handle, output_format, to_stderr);
+                }
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    let follow_referral = cmd_line_args.is_present("follow-referral");
+    // The registries ARIN may refer a lookup to with --follow-referral
+    let referral_clients: Vec<_> = if follow_referral {
+        source::REFERRAL_HANDLES.iter()
+            .filter_map(|&handle| Registry::from_referral(handle).map(|registry| (handle, registry)))
+            .map(|(handle, registry)| (handle, client_for(registry, WhoisParser::for_registry(registry))))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let by_url = input_url.is_some();
+    let bulk_content = cmd_line_args.value_of("bulk-file").map(|path| {
+        let mut content = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut content)).unwrap_or_else(|e| {
+            eprintln!("Failed to read {}: {}", path, e);
+            process::exit(1);
+        });
+        content
+    });
+    let seed = value_t!(cmd_line_args, "seed", u64).unwrap_or_else(|e| e.exit());
+    if demo {
+        eprintln!("Warning: --demo prints made-up ranges, not real ARIN data");
+    }
+    let org_hierarchy = cmd_line_args.is_present("org-hierarchy");
+    let include_children = org_hierarchy || cmd_line_args.is_present("include-children");
+    let with_abuse_contact = cmd_line_args.is_present("with-abuse-contact");
+    let resolve_details = org_hierarchy || with_abuse_contact || cmd_line_args.is_present("resolve-details");
+    let lookup = move |handle: &str| -> Result<WhoisResult, WhoisError> {
+        let mut result = if demo {
+            demo::demo_result(seed)
+        } else if by_url {
+            client.get_url(handle)?
+        } else if let Some(ip) = lookup_ip {
+            client.get_ip(ip)?
+        } else if let Some(asn) = lookup_asn {
+            client.get_asn(asn)?
+        } else if let Some(ref content) = bulk_content {
+            BulkFileParser::new(handle).parse(content)?
+        } else if follow_referral {
+            let registries: Vec<(&str, &dyn WhoisSource)> = referral_clients.iter()
+                .map(|&(name, ref referral_client)| (name, referral_client as &dyn WhoisSource))
+                .collect();
+            source::lookup_following_referrals(&client, handle, &registries, source::MAX_REFERRAL_DEPTH)?
+        } else {
+            client.get(handle)?
+        };
+        if include_children {
+            client.include_children(&mut result, MAX_CHILD_DEPTH)?;
+        }
+        if resolve_details {
+            client.resolve_details(&mut result)?;
+        }
+        if with_abuse_contact {
+            client.resolve_abuse_contacts(&mut result)?;
+        }
+        Ok(result)
+    };
+    let timeout_per_handle = if cmd_line_args.is_present("timeout-per-handle") {
+        let seconds = value_t!(cmd_line_args, "timeout-per-handle", u64).unwrap_or_else(|e| e.exit());
+        Some(Duration::from_secs(seconds))
+    } else {
+        None
+    };
+    let jobs = value_t!(cmd_line_args, "jobs", usize).unwrap_or_else(|e| e.exit());
+    let responses = batch::lookup_all(Arc::new(lookup), &handles, timeout_per_handle, jobs);
 
-    if response.is_err() {
-        println!("{:}", response.unwrap_err());
+    let mut explain = Explain::new();
+    let mut result = WhoisResult::new(Vec::new());
+    let mut failed = false;
+    let mut found = 0;
+    for (handle, response) in handles.iter().zip(responses) {
+        match response {
+            Ok(response) => {
+                found += response.ips.len();
+                for fetch in &response.fetches {
+                    explain.step("url", &fetch.url);
+                    explain.step("http status", fetch.status);
+                    if fetch.retry_count > 0 {
+                        explain.step("retries", fetch.retry_count);
+                    }
+                }
+                explain.step("nets parsed", response.ips.len());
+                for warning in &response.warnings {
+                    eprintln!("Warning: {}: {}", handle, warning);
+                }
+                if let Some(ref referral) = response.referral {
+                    eprintln!("Warning: ARIN refers {} to {}, see --follow-referral", handle, referral);
+                }
+                if org_hierarchy {
+                    hierarchy::write_tree(handle, &response.ips, &mut out).unwrap();
+                } else if handles.len() > 1 {
+                    result.ips.extend(response.ips.into_iter().map(|mut ip| {
+                        ip.lookup = Some(handle.clone());
+                        ip
+                    }));
+                } else {
+                    result.ips.extend(response.ips);
+                }
+            }
+            Err(err) => {
+                failed = true;
+                let handle = if handles.len() > 1 { Some(handle.as_ref()) } else { None };
+                report_error(&err, handle, output_format, to_stderr);
+            }
+        }
+    }
+    // A single failed lookup has nothing else worth printing
+    if failed && handles.len() == 1 {
+        process::exit(1);
+    }
+    if cmd_line_args.is_present("fail-on-empty") && !failed {
+        if let Some(message) = empty_result_message(&handles, found) {
+            eprintln!("{}", message);
+            process::exit(EXIT_EMPTY);
+        }
+    }
+    if org_hierarchy {
+        if failed {
+            process::exit(1);
+        }
         return;
     }
 
-    for ip in response.unwrap().ips {
-        let range = IpAddrRange::from_range(ip.start_ip, ip.end_ip);
-        println!("{}", range.unwrap());
+    let mut transforms = Transforms::default();
+    if cmd_line_args.is_present("ipv4-only") {
+        transforms.only_family = Some(Family::V4);
+    } else if cmd_line_args.is_present("ipv6-only") {
+        transforms.only_family = Some(Family::V6);
+    }
+    transforms.prefer_family = cmd_line_args.value_of("prefer-family").map(|family| Family::from_str(family).unwrap());
+    transforms.dedup = match cmd_line_args.value_of("dedup-by") {
+        Some(key) => Some(DedupKey::from_str(key).unwrap()),
+        None if cmd_line_args.is_present("dedup") => Some(DedupKey::Range),
+        None => None,
+    };
+    if let Some(registries) = cmd_line_args.values_of("rir-precedence") {
+        transforms.rir_precedence = registries.map(|registry| registry.to_lowercase()).collect();
+    }
+    transforms.merge = cmd_line_args.is_present("merge");
+    transforms.aggregate = cmd_line_args.is_present("aggregate");
+    transforms.sort_order = match cmd_line_args.value_of("sort-by") {
+        Some(order) => Some(SortOrder::from_str(order).unwrap()),
+        None if cmd_line_args.is_present("sort") => Some(SortOrder::Start),
+        None => None,
+    };
+    if cmd_line_args.is_present("normalize") {
+        transforms.normalize();
+    }
+    if cmd_line_args.is_present("sample") {
+        transforms.sample = Some(value_t!(cmd_line_args, "sample", usize).unwrap_or_else(|e| e.exit()));
+    }
+    transforms.ascii_names = cmd_line_args.is_present("ascii-names");
+    result.transform(&transforms, &mut explain);
+    explain.step("output", result.ips.len());
+    if cmd_line_args.is_present("explain") {
+        explain.write(&mut io::stderr()).unwrap();
+    }
+    if cmd_line_args.is_present("stats") {
+        output::write_stats(&result, count_format, &mut io::stderr()).unwrap();
+    }
+
+    if cmd_line_args.is_present("hash") {
+        writeln!(out, "{}", result.content_hash()).unwrap();
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    if cmd_line_args.is_present("count") {
+        writeln!(out, "{}", output::format_count(result.total_address_count(), count_format)).unwrap();
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    if let Some(path) = cmd_line_args.value_of("diff-against") {
+        let earlier = read_prefixes(path).unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            process::exit(1);
+        });
+        let prefixes = result.ips
+            .iter()
+            .flat_map(|ip| ip.cidrs().map(move |net| WhoisIpResult::new(ip.name.clone(), net.addr(), net.last_addr())))
+            .collect();
+        let diff = WhoisResult::new(prefixes).diff(&earlier);
+        for ip in &diff.added {
+            writeln!(out, "+ {}", ip.cidrs().next().unwrap()).unwrap();
+        }
+        for ip in &diff.removed {
+            writeln!(out, "- {}", ip.cidrs().next().unwrap()).unwrap();
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    if let Some(supernet) = relative_to {
+        for ip in &result.ips {
+            match supernet.offset_of(ip.start_ip, ip.end_ip) {
+                Some((offset, length)) => writeln!(out, "offset={} length={}", offset, length).unwrap(),
+                None => eprintln!("Warning: {} - {} is not within {}", ip.start_ip, ip.end_ip, supernet),
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    if output_format == OutputFormat::Nmap {
+        let nets: Vec<IpNet> = match complement_within {
+            Some(supernet) => result.complement_within(supernet),
+            None => result.ips.iter().flat_map(|ip| ip.cidrs()).collect(),
+        };
+        let (_, skipped) = output::nmap_targets(&nets);
+        if skipped > 0 {
+            eprintln!("Warning: left out {} IPv6 prefixes, which nmap only scans with -6", skipped);
+        }
+    }
+    match complement_within {
+        Some(supernet) => {
+            output::write_nets(&result.complement_within(supernet), output_format, &output_options, &mut out).unwrap()
+        }
+        None => output::write_result(&result, output_format, &output_options, &mut out).unwrap(),
+    }
+    if failed {
+        process::exit(1);
     }
 }
 
 
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use log::LevelFilter;
+
+    use super::{empty_result_message, log_level, parse_handles, read_handles};
+
+    #[test]
+    fn fail_on_empty() {
+        let handles = vec![String::from("DROPB"), String::from("EXAMPLE")];
+        assert_eq!(empty_result_message(&handles, 0), Some(String::from("No ranges found for DROPB, EXAMPLE")));
+        assert_eq!(empty_result_message(&handles, 1), None);
+    }
+
+    #[test]
+    fn parse_handles_skips_blanks_and_comments() {
+        let content = "# Acquisitions\nDROPB\n\n  EXAMPLE  \n   \n#DISABLED\nGOGL\n";
+        assert_eq!(parse_handles(content), vec!["DROPB", "EXAMPLE", "GOGL"]);
+        assert!(parse_handles("# nothing yet\n\n").is_empty());
+    }
+
+    #[test]
+    fn read_handles_from_reader() {
+        let stdin = &b"DROPB\r\n# from the asset inventory\r\nEXAMPLE\r\n"[..];
+        assert_eq!(read_handles(stdin).unwrap(), vec!["DROPB", "EXAMPLE"]);
+    }
+
+    #[test]
+    fn log_level_per_verbosity() {
+        assert_eq!(log_level(0), None);
+        assert_eq!(log_level(1), Some(LevelFilter::Info));
+        assert_eq!(log_level(2), Some(LevelFilter::Debug));
+        assert_eq!(log_level(5), Some(LevelFilter::Trace));
+    }
+}
@@ -0,0 +1,1039 @@
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde_json;
+
+use super::{WhoisResult, WhoisIpResult};
+use bogons;
+use cidr::IpNet;
+use error::{WhoisError, WhoisErrorKind};
+use xmlparser::PocLink;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Each range's CIDR prefixes, one per line
+    Plain,
+    /// A JSON array of ranges, or a JSON error object
+    Json,
+    /// The same counts as `write_stats` instead of the ranges
+    Summary,
+    /// One JSON object per range per line
+    Ndjson,
+    /// Like `Ndjson`, but every record has a `type`, `net` for the ranges,
+    /// and the stream ends with a `summary` record of `write_stats`' counts
+    JsonStream,
+    /// Every range's CIDR prefixes on a single line, joined by commas
+    OneLine,
+    /// One CIDR prefix per line for zmap or masscan, leaving out bogons
+    /// unless `OutputOptions::include_bogons` is set
+    Zmap,
+    /// A Terraform `locals` block holding every CIDR prefix in `org_cidrs`
+    Tf,
+    /// Cisco ACL entries, `permit ip <network> <wildcard>` for IPv4 and
+    /// `permit ipv6 <prefix>` for IPv6
+    Cisco,
+    /// One IPv4 CIDR prefix per line as nmap targets, e.g. for `nmap -iL`.
+    /// nmap only scans IPv6 with `-6` and a list of its own, so IPv6
+    /// prefixes are left out, see `nmap_targets`
+    Nmap,
+    /// `ipset add` commands putting the IPv4 prefixes in the set
+    /// `OutputOptions::ipset_name` and the IPv6 ones in the same name with
+    /// `-v6` appended, as a set only holds a single family
+    Ipset,
+    /// A `name,start_ip,end_ip` header and one row per range, written in
+    /// the given dialect
+    Csv(CsvDialect),
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s {
+            "plain" => Ok(OutputFormat::Plain),
+            "json" => Ok(OutputFormat::Json),
+            "summary" => Ok(OutputFormat::Summary),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "json-stream" => Ok(OutputFormat::JsonStream),
+            "oneline" => Ok(OutputFormat::OneLine),
+            "zmap" => Ok(OutputFormat::Zmap),
+            "tf" => Ok(OutputFormat::Tf),
+            "cisco" => Ok(OutputFormat::Cisco),
+            "nmap" => Ok(OutputFormat::Nmap),
+            "ipset" => Ok(OutputFormat::Ipset),
+            "csv" => Ok(OutputFormat::Csv(CsvDialect::default())),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// When `Csv` output puts a field in double quotes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvQuote {
+    /// Every field
+    Always,
+    /// Only fields holding the delimiter, a quote or a line break
+    Minimal,
+}
+
+impl FromStr for CsvQuote {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CsvQuote, String> {
+        match s {
+            "always" => Ok(CsvQuote::Always),
+            "minimal" => Ok(CsvQuote::Minimal),
+            _ => Err(format!("Unknown CSV quoting: {}", s)),
+        }
+    }
+}
+
+/// How `Csv` output separates and quotes fields, e.g. with `;` for
+/// spreadsheets in locales using `,` as the decimal separator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvDialect {
+    pub delimiter: char,
+    pub quote: CsvQuote,
+}
+
+impl Default for CsvDialect {
+    fn default() -> CsvDialect {
+        CsvDialect {
+            delimiter: ',',
+            quote: CsvQuote::Minimal,
+        }
+    }
+}
+
+impl CsvDialect {
+    /// Parse a `--csv-delimiter`: a single character, or `\t` for a tab.
+    pub fn parse_delimiter(s: &str) -> Result<char, String> {
+        if s == "\\t" {
+            return Ok('\t');
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some('"'), None) | (Some('\r'), None) | (Some('\n'), None) => {
+                Err(format!("CSV delimiter cannot be a quote or line break: {:?}", s))
+            }
+            (Some(delimiter), None) => Ok(delimiter),
+            _ => Err(format!("CSV delimiter must be a single character: {:?}", s)),
+        }
+    }
+
+    fn write_row<W: Write>(&self, fields: &[&str], w: &mut W) -> io::Result<()> {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                write!(w, "{}", self.delimiter)?;
+            }
+            let needs_quotes = self.quote == CsvQuote::Always ||
+                               field.contains(|c| c == self.delimiter || c == '"' || c == '\r' || c == '\n');
+            if needs_quotes {
+                write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                write!(w, "{}", field)?;
+            }
+        }
+        writeln!(w)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountFormat {
+    /// Plain digits, e.g. `65536`
+    Raw,
+    /// Digits grouped in thousands, e.g. `65,536`
+    Grouped,
+}
+
+impl FromStr for CountFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CountFormat, String> {
+        match s {
+            "raw" => Ok(CountFormat::Raw),
+            "grouped" => Ok(CountFormat::Grouped),
+            _ => Err(format!("Unknown count format: {}", s)),
+        }
+    }
+}
+
+/// An optional per-range field, chosen with `--fields`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// The range's decomposition into CIDR prefixes
+    Cidr,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Field, String> {
+        match s {
+            "cidr" => Ok(Field::Cidr),
+            _ => Err(format!("Unknown field: {}", s)),
+        }
+    }
+}
+
+/// The set `Ipset` output adds to unless `OutputOptions::ipset_name` is set.
+pub const DEFAULT_IPSET_NAME: &str = "whois-ips";
+
+/// ipset's own limit is 31 characters, less the `-v6` of the IPv6 set.
+const MAX_IPSET_NAME_LEN: usize = 28;
+
+/// Parse an `--ipset-name`, which ipset limits in length and which has to be
+/// a single shell word.
+pub fn parse_ipset_name(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.len() > MAX_IPSET_NAME_LEN {
+        return Err(format!("ipset name must be 1 to {} characters: {:?}", MAX_IPSET_NAME_LEN, s));
+    }
+    if !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err(format!("ipset name can only hold letters, digits, '-', '_' and '.': {:?}", s));
+    }
+    Ok(s.to_string())
+}
+
+/// Settings that change what some formats write.
+#[derive(Debug, Default)]
+pub struct OutputOptions {
+    /// Extra per-range fields for `Json`
+    pub fields: Vec<Field>,
+    /// Keep bogon space in `Zmap` output
+    pub include_bogons: bool,
+    /// The set for `Ipset` output, `DEFAULT_IPSET_NAME` if `None`
+    pub ipset_name: Option<String>,
+}
+
+/// A step by step account of a lookup, for `--explain`.
+#[derive(Debug, Default)]
+pub struct Explain {
+    steps: Vec<(String, String)>,
+}
+
+impl Explain {
+    pub fn new() -> Explain {
+        Explain { steps: Vec::new() }
+    }
+
+    pub fn step<T: ToString>(&mut self, name: &str, value: T) {
+        self.steps.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for &(ref name, ref value) in &self.steps {
+            writeln!(w, "{}: {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct JsonNet<'a> {
+    /// The record type, only set for `JsonStream`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    record_type: Option<&'static str>,
+    name: &'a str,
+    start_ip: IpAddr,
+    end_ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_ases: Option<&'a [u32]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cidrs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    abuse_email: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registration_date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lookup: Option<&'a str>,
+}
+
+/// The last record of a `JsonStream`, telling consumers it is complete.
+#[derive(Serialize)]
+struct JsonSummary {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    ranges: usize,
+    cidrs: usize,
+    /// A string, as IPv6 counts are too big for most JSON parsers' numbers
+    addresses: String,
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    record_type: Option<&'static str>,
+    error: JsonErrorDetail<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonErrorDetail<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handle: Option<&'a str>,
+    kind: WhoisErrorKind,
+    message: String,
+}
+
+impl<'a> JsonNet<'a> {
+    fn new(ip: &'a WhoisIpResult, fields: &[Field]) -> JsonNet<'a> {
+        JsonNet {
+            record_type: None,
+            name: &ip.name,
+            start_ip: ip.start_ip,
+            end_ip: ip.end_ip,
+            source: ip.source.as_ref().map(|source| source.as_str()),
+            origin_ases: if ip.origin_ases.is_empty() {
+                None
+            } else {
+                Some(&ip.origin_ases)
+            },
+            comments: if ip.comments.is_empty() {
+                None
+            } else {
+                Some(&ip.comments)
+            },
+            cidrs: if fields.contains(&Field::Cidr) {
+                Some(ip.cidrs().map(|cidr| cidr.to_string()).collect())
+            } else {
+                None
+            },
+            abuse_email: ip.abuse_email.as_ref().map(|email| email.as_str()),
+            registration_date: ip.registration_date.as_ref().map(|date| date.as_str()),
+            update_date: ip.update_date.as_ref().map(|date| date.as_str()),
+            lookup: ip.lookup.as_ref().map(|lookup| lookup.as_str()),
+        }
+    }
+}
+
+/// The ranges as a JSON array, with the optional `fields` added to each.
+pub fn to_ranges_json(result: &WhoisResult, fields: &[Field]) -> serde_json::Result<String> {
+    let nets: Vec<JsonNet> = result.ips.iter().map(|ip| JsonNet::new(ip, fields)).collect();
+    serde_json::to_string(&nets)
+}
+
+pub fn write_result<W: Write>(result: &WhoisResult,
+                              format: OutputFormat,
+                              options: &OutputOptions,
+                              w: &mut W)
+                              -> io::Result<()> {
+    match format {
+        OutputFormat::Json => writeln!(w, "{}", to_ranges_json(result, &options.fields)?),
+        OutputFormat::Ndjson => {
+            for ip in &result.ips {
+                serde_json::to_writer(&mut *w, &JsonNet::new(ip, &options.fields))?;
+                writeln!(w)?;
+            }
+            Ok(())
+        }
+        OutputFormat::JsonStream => {
+            for ip in &result.ips {
+                let mut net = JsonNet::new(ip, &options.fields);
+                net.record_type = Some("net");
+                serde_json::to_writer(&mut *w, &net)?;
+                writeln!(w)?;
+            }
+            let summary = JsonSummary {
+                record_type: "summary",
+                ranges: result.ips.len(),
+                cidrs: result.cidr_count(),
+                addresses: result.total_address_count().to_string(),
+            };
+            serde_json::to_writer(&mut *w, &summary)?;
+            writeln!(w)
+        }
+        OutputFormat::Summary => write_stats(result, CountFormat::Raw, w),
+        OutputFormat::Csv(dialect) => {
+            // Only for several handles and with --with-abuse-contact, so other
+            // output keeps its columns
+            let lookups = result.ips.iter().any(|ip| ip.lookup.is_some());
+            let abuse_emails = result.ips.iter().any(|ip| ip.abuse_email.is_some());
+            let mut header = Vec::new();
+            if lookups {
+                header.push("lookup");
+            }
+            header.extend_from_slice(&["name", "start_ip", "end_ip"]);
+            if abuse_emails {
+                header.push("abuse_email");
+            }
+            dialect.write_row(&header, w)?;
+            for ip in &result.ips {
+                let (start_ip, end_ip) = (ip.start_ip.to_string(), ip.end_ip.to_string());
+                let mut row = Vec::new();
+                if lookups {
+                    row.push(ip.lookup.as_ref().map(|lookup| lookup.as_str()).unwrap_or(""));
+                }
+                row.extend_from_slice(&[&ip.name, &start_ip, &end_ip]);
+                if abuse_emails {
+                    row.push(ip.abuse_email.as_ref().map(|email| email.as_str()).unwrap_or(""));
+                }
+                dialect.write_row(&row, w)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Plain if result.ips.iter().any(|ip| ip.lookup.is_some()) => {
+            // Several handles were looked up, so each one's prefixes follow a
+            // `# <handle>` line
+            let mut lookup = None;
+            for ip in &result.ips {
+                if ip.lookup.is_some() && ip.lookup != lookup {
+                    lookup = ip.lookup.clone();
+                    writeln!(w, "# {}", ip.lookup.as_ref().unwrap())?;
+                }
+                for net in ip.cidrs() {
+                    writeln!(w, "{}", net)?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Plain | OutputFormat::OneLine | OutputFormat::Tf | OutputFormat::Cisco | OutputFormat::Nmap |
+        OutputFormat::Ipset => {
+            let nets: Vec<IpNet> = result.ips.iter().flat_map(|ip| ip.cidrs()).collect();
+            write_nets(&nets, format, options, w)
+        }
+        OutputFormat::Zmap => {
+            let nets: Vec<IpNet> = result.ips.iter().flat_map(|ip| ip.cidrs()).collect();
+            if options.include_bogons {
+                write_nets(&nets, format, options, w)
+            } else {
+                write_nets(&bogons::exclude(&nets), format, options, w)
+            }
+        }
+    }
+}
+
+/// Write `result` as `Csv` in the default dialect: comma separated, with
+/// IPv6 addresses in their compressed form.
+pub fn write_csv<W: Write>(result: &WhoisResult, w: &mut W) -> io::Result<()> {
+    write_result(result, OutputFormat::Csv(CsvDialect::default()), &OutputOptions::default(), w)
+}
+
+/// The prefixes `Nmap` output lists as targets, and how many IPv6 prefixes
+/// it leaves out, so callers can warn about them.
+pub fn nmap_targets(nets: &[IpNet]) -> (Vec<IpNet>, usize) {
+    let targets: Vec<IpNet> = nets.iter().filter(|net| net.addr().is_ipv4()).cloned().collect();
+    let skipped = nets.len() - targets.len();
+    (targets, skipped)
+}
+
+/// Write bare prefixes, e.g. from `WhoisResult::complement_within`: one per
+/// line, a JSON array of strings, a Terraform snippet, or a `cidr` column.
+pub fn write_nets<W: Write>(nets: &[IpNet],
+                            format: OutputFormat,
+                            options: &OutputOptions,
+                            w: &mut W)
+                            -> io::Result<()> {
+    match format {
+        OutputFormat::Plain | OutputFormat::Summary | OutputFormat::Zmap => {
+            for net in nets {
+                writeln!(w, "{}", net)?;
+            }
+            Ok(())
+        }
+        OutputFormat::OneLine => {
+            let nets: Vec<String> = nets.iter().map(|net| net.to_string()).collect();
+            writeln!(w, "{}", nets.join(","))
+        }
+        OutputFormat::Json => {
+            let nets: Vec<String> = nets.iter().map(|net| net.to_string()).collect();
+            serde_json::to_writer(&mut *w, &nets)?;
+            writeln!(w)
+        }
+        OutputFormat::Ndjson => {
+            for net in nets {
+                serde_json::to_writer(&mut *w, &net.to_string())?;
+                writeln!(w)?;
+            }
+            Ok(())
+        }
+        OutputFormat::JsonStream => {
+            for net in nets {
+                writeln!(w, "{{\"type\":\"prefix\",\"cidr\":{}}}", serde_json::to_string(&net.to_string())?)?;
+            }
+            writeln!(w, "{{\"type\":\"summary\",\"cidrs\":{}}}", nets.len())
+        }
+        OutputFormat::Tf => {
+            writeln!(w, "# Generated by whois-ips, {} CIDR prefixes", nets.len())?;
+            writeln!(w, "locals {{")?;
+            writeln!(w, "  org_cidrs = [")?;
+            for net in nets {
+                // JSON strings are valid HCL strings
+                writeln!(w, "    {},", serde_json::to_string(&net.to_string())?)?;
+            }
+            writeln!(w, "  ]")?;
+            writeln!(w, "}}")
+        }
+        OutputFormat::Csv(dialect) => {
+            dialect.write_row(&["cidr"], w)?;
+            for net in nets {
+                dialect.write_row(&[&net.to_string()], w)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Cisco => {
+            for net in nets {
+                match net.addr() {
+                    IpAddr::V4(addr) => writeln!(w, "permit ip {} {} any", addr, net.wildcard_mask())?,
+                    IpAddr::V6(_) => writeln!(w, "permit ipv6 {} any", net)?,
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Nmap => {
+            for net in nmap_targets(nets).0 {
+                writeln!(w, "{}", net)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Ipset => {
+            let set_name = options.ipset_name.as_ref().map_or(DEFAULT_IPSET_NAME, |name| name.as_str());
+            for net in nets {
+                match net.addr() {
+                    IpAddr::V4(_) => writeln!(w, "ipset add {} {}", set_name, net)?,
+                    IpAddr::V6(_) => writeln!(w, "ipset add {}-v6 {}", set_name, net)?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write the points of contact of `org` for `--list-pocs`: a JSON array of
+/// them, an object per line, or `<org> <role> <handle>` lines.
+pub fn write_pocs<W: Write>(org: &str, pocs: &[PocLink], format: OutputFormat, w: &mut W) -> io::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut *w, pocs)?;
+            writeln!(w)
+        }
+        OutputFormat::Ndjson => {
+            for poc in pocs {
+                serde_json::to_writer(&mut *w, poc)?;
+                writeln!(w)?;
+            }
+            Ok(())
+        }
+        _ => {
+            for poc in pocs {
+                writeln!(w, "{} {} {}", org, poc.role, poc.handle)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write a failed lookup's error, naming the handle it was for if given.
+pub fn write_error<W: Write>(err: &WhoisError,
+                             handle: Option<&str>,
+                             format: OutputFormat,
+                             w: &mut W)
+                             -> io::Result<()> {
+    match format {
+        OutputFormat::Plain | OutputFormat::Summary | OutputFormat::OneLine | OutputFormat::Zmap |
+        OutputFormat::Tf | OutputFormat::Cisco | OutputFormat::Nmap | OutputFormat::Ipset | OutputFormat::Csv(_) => {
+            match handle {
+                Some(handle) => writeln!(w, "{}: {}", handle, err),
+                None => writeln!(w, "{}", err),
+            }
+        }
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonStream => {
+            let json_error = JsonError {
+                record_type: if format == OutputFormat::JsonStream { Some("error") } else { None },
+                error: JsonErrorDetail {
+                    handle: handle,
+                    kind: err.kind(),
+                    message: err.to_string(),
+                },
+            };
+            serde_json::to_writer(&mut *w, &json_error)?;
+            writeln!(w)
+        }
+    }
+}
+
+/// `s` with every non-ASCII character replaced by its Rust style escape,
+/// e.g. `Soci\u{e9}t\u{e9}` for `Société`, for `--ascii-names`.
+pub fn escape_non_ascii(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii() {
+            c.to_string()
+        } else {
+            format!("\\u{{{:x}}}", c as u32)
+        })
+        .collect()
+}
+
+pub fn format_count(count: u128, format: CountFormat) -> String {
+    let digits = count.to_string();
+    match format {
+        CountFormat::Raw => digits,
+        CountFormat::Grouped => {
+            let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+            for (i, digit) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i) % 3 == 0 {
+                    grouped.push(',');
+                }
+                grouped.push(digit);
+            }
+            grouped
+        }
+    }
+}
+
+pub fn write_stats<W: Write>(result: &WhoisResult, count_format: CountFormat, w: &mut W) -> io::Result<()> {
+    writeln!(w, "ranges: {}", format_count(result.ips.len() as u128, count_format))?;
+    writeln!(w, "cidrs: {}", format_count(result.cidr_count() as u128, count_format))?;
+    writeln!(w, "addresses: {}", format_count(result.total_address_count(), count_format))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::io::Write;
+    use std::net::IpAddr;
+
+    use hyper::status::StatusCode;
+
+    use super::{CountFormat, CsvDialect, CsvQuote, Field, OutputFormat, OutputOptions, escape_non_ascii, format_count,
+                nmap_targets, parse_ipset_name,
+                to_ranges_json, write_csv, write_stats, write_result, write_error};
+    use super::super::{WhoisResult, WhoisIpResult};
+    use cidr::IpNet;
+    use error::WhoisError;
+    use httpclient::HttpClientError;
+    use xmlparser::PocLink;
+
+    fn dropbox_result() -> WhoisResult {
+        WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                 IpAddr::from_str("162.125.0.0").unwrap(),
+                                                 IpAddr::from_str("162.125.255.255").unwrap())])
+    }
+
+    #[test]
+    fn escape_non_ascii_names() {
+        assert_eq!(escape_non_ascii("DROPB"), "DROPB");
+        assert_eq!(escape_non_ascii("Société Générale"), "Soci\\u{e9}t\\u{e9} G\\u{e9}n\\u{e9}rale");
+        assert_eq!(escape_non_ascii("株式会社"), "\\u{682a}\\u{5f0f}\\u{4f1a}\\u{793e}");
+    }
+
+    #[test]
+    fn format_count_raw() {
+        assert_eq!(format_count(0, CountFormat::Raw), "0");
+        assert_eq!(format_count(65536, CountFormat::Raw), "65536");
+    }
+
+    #[test]
+    fn format_count_grouped() {
+        assert_eq!(format_count(0, CountFormat::Grouped), "0");
+        assert_eq!(format_count(256, CountFormat::Grouped), "256");
+        assert_eq!(format_count(1000, CountFormat::Grouped), "1,000");
+        assert_eq!(format_count(65536, CountFormat::Grouped), "65,536");
+        assert_eq!(format_count(16777216, CountFormat::Grouped), "16,777,216");
+    }
+
+    #[test]
+    fn count_format_from_str() {
+        assert_eq!(CountFormat::from_str("raw"), Ok(CountFormat::Raw));
+        assert_eq!(CountFormat::from_str("grouped"), Ok(CountFormat::Grouped));
+        assert!(CountFormat::from_str("commas").is_err());
+    }
+
+    #[test]
+    fn write_stats_both_formats() {
+        let result = dropbox_result();
+
+        let mut raw = Vec::new();
+        write_stats(&result, CountFormat::Raw, &mut raw).unwrap();
+        assert_eq!(String::from_utf8(raw).unwrap(), "ranges: 1\ncidrs: 1\naddresses: 65536\n");
+
+        let mut grouped = Vec::new();
+        write_stats(&result, CountFormat::Grouped, &mut grouped).unwrap();
+        assert_eq!(String::from_utf8(grouped).unwrap(), "ranges: 1\ncidrs: 1\naddresses: 65,536\n");
+    }
+
+    #[test]
+    fn write_result_plain() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("TEST"),
+                                                              IpAddr::from_str("192.0.2.1").unwrap(),
+                                                              IpAddr::from_str("192.0.2.10").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST6"),
+                                                              IpAddr::from_str("2001:db8::").unwrap(),
+                                                              IpAddr::from_str("2001:db8::2").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Plain, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "192.0.2.1/32\n192.0.2.2/31\n192.0.2.4/30\n192.0.2.8/31\n192.0.2.10/32\n\
+                    2001:db8::/127\n2001:db8::2/128\n");
+    }
+
+    #[test]
+    fn write_result_json() {
+        let mut out = Vec::new();
+        write_result(&dropbox_result(), OutputFormat::Json, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\"}]\n");
+    }
+
+    #[test]
+    fn write_result_to_trait_object() {
+        // As for --output, which picks a file or stdout at runtime
+        let mut buf = Vec::new();
+        {
+            let mut out: &mut dyn Write = &mut buf;
+            write_result(&dropbox_result(), OutputFormat::Json, &OutputOptions::default(), &mut out).unwrap();
+            write_result(&dropbox_result(), OutputFormat::Plain, &OutputOptions::default(), &mut out).unwrap();
+        }
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\"}]\n\
+                    162.125.0.0/16\n");
+    }
+
+    #[test]
+    fn write_result_ndjson() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST6"),
+                                                              IpAddr::from_str("2001:db8::").unwrap(),
+                                                              IpAddr::from_str("2001:db8::ff:ffff").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Ndjson, &OutputOptions::default(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), result.ips.len());
+        assert_eq!(lines,
+                   vec!["{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\"}",
+                        "{\"name\":\"TEST6\",\"start_ip\":\"2001:db8::\",\"end_ip\":\"2001:db8::ff:ffff\"}"]);
+    }
+
+    #[test]
+    fn write_result_oneline() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.2.255").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST6"),
+                                                              IpAddr::from_str("2001:db8::").unwrap(),
+                                                              IpAddr::from_str("2001:db8::ff:ffff").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::OneLine, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "162.125.0.0/23,162.125.2.0/24,2001:db8::/104\n");
+    }
+
+    #[test]
+    fn write_result_zmap() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("PRIVATE"),
+                                                              IpAddr::from_str("192.168.0.0").unwrap(),
+                                                              IpAddr::from_str("192.168.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST6"),
+                                                              IpAddr::from_str("2001:db8::").unwrap(),
+                                                              IpAddr::from_str("2001:db8::ff:ffff").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Zmap, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "162.125.0.0/16\n");
+
+        let options = OutputOptions { include_bogons: true, ..OutputOptions::default() };
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Zmap, &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "162.125.0.0/16\n192.168.0.0/16\n2001:db8::/104\n");
+    }
+
+    #[test]
+    fn write_result_tf() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.2.255").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST6"),
+                                                              IpAddr::from_str("2001:db8::").unwrap(),
+                                                              IpAddr::from_str("2001:db8::ffff").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Tf, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "# Generated by whois-ips, 3 CIDR prefixes\n\
+                    locals {\n  \
+                    org_cidrs = [\n    \
+                    \"162.125.0.0/23\",\n    \
+                    \"162.125.2.0/24\",\n    \
+                    \"2001:db8::/112\",\n  \
+                    ]\n\
+                    }\n");
+    }
+
+    #[test]
+    fn write_result_json_stream() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("TEST"),
+                                                              IpAddr::from_str("192.0.2.1").unwrap(),
+                                                              IpAddr::from_str("192.0.2.10").unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::JsonStream, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"type\":\"net\",\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\"}\n\
+                    {\"type\":\"net\",\"name\":\"TEST\",\"start_ip\":\"192.0.2.1\",\"end_ip\":\"192.0.2.10\"}\n\
+                    {\"type\":\"summary\",\"ranges\":2,\"cidrs\":6,\"addresses\":\"65546\"}\n");
+
+        let mut out = Vec::new();
+        write_result(&WhoisResult::new(Vec::new()), OutputFormat::JsonStream, &OutputOptions::default(), &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"type\":\"summary\",\"ranges\":0,\"cidrs\":0,\"addresses\":\"0\"}\n");
+    }
+
+    #[test]
+    fn write_result_cisco() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("DROPBOX-V6"),
+                                                              IpAddr::from_str("2620:100:6000::").unwrap(),
+                                                              IpAddr::from_str("2620:100:6000:ffff:ffff:ffff:ffff:ffff")
+                                                                  .unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Cisco, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "permit ip 162.125.0.0 0.0.255.255 any\npermit ipv6 2620:100:6000::/48 any\n");
+    }
+
+    #[test]
+    fn write_result_nmap() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("ALIGNED"),
+                                                              IpAddr::from_str("198.51.100.0").unwrap(),
+                                                              IpAddr::from_str("198.51.103.255").unwrap()),
+                                           WhoisIpResult::new(String::from("ACROSS-22"),
+                                                              IpAddr::from_str("10.0.2.0").unwrap(),
+                                                              IpAddr::from_str("10.0.5.255").unwrap()),
+                                           WhoisIpResult::new(String::from("DROPBOX-V6"),
+                                                              IpAddr::from_str("2620:100:6000::").unwrap(),
+                                                              IpAddr::from_str("2620:100:6000:ffff:ffff:ffff:ffff:ffff")
+                                                                  .unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Nmap, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "198.51.100.0/22\n10.0.2.0/23\n10.0.4.0/23\n");
+        let nets: Vec<IpNet> = result.ips.iter().flat_map(|ip| ip.cidrs()).collect();
+        let (targets, skipped) = nmap_targets(&nets);
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|net| net.addr().is_ipv4()));
+        assert_eq!(skipped, 1);
+        assert_eq!(OutputFormat::from_str("nmap"), Ok(OutputFormat::Nmap));
+    }
+
+    #[test]
+    fn write_result_ipset() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("ACROSS-22"),
+                                                              IpAddr::from_str("10.0.2.0").unwrap(),
+                                                              IpAddr::from_str("10.0.5.255").unwrap()),
+                                           WhoisIpResult::new(String::from("DROPBOX-V6"),
+                                                              IpAddr::from_str("2620:100:6000::").unwrap(),
+                                                              IpAddr::from_str("2620:100:6000:ffff:ffff:ffff:ffff:ffff")
+                                                                  .unwrap())]);
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Ipset, &OutputOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "ipset add whois-ips 10.0.2.0/23\nipset add whois-ips 10.0.4.0/23\n\
+                    ipset add whois-ips-v6 2620:100:6000::/48\n");
+
+        let options = OutputOptions { ipset_name: Some(String::from("dropbox")), ..OutputOptions::default() };
+        let mut out = Vec::new();
+        write_result(&result, OutputFormat::Ipset, &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "ipset add dropbox 10.0.2.0/23\nipset add dropbox 10.0.4.0/23\n\
+                    ipset add dropbox-v6 2620:100:6000::/48\n");
+    }
+
+    #[test]
+    fn ipset_names() {
+        assert_eq!(parse_ipset_name("dropbox_nets"), Ok(String::from("dropbox_nets")));
+        assert!(parse_ipset_name("").is_err());
+        assert!(parse_ipset_name("two words").is_err());
+        assert!(parse_ipset_name(&"x".repeat(28)).is_ok());
+        assert!(parse_ipset_name(&"x".repeat(29)).is_err());
+    }
+
+    #[test]
+    fn write_result_per_lookup() {
+        let looked_up = |lookup: &str, name: &str, start: &str, end: &str| {
+            let mut ip = WhoisIpResult::new(String::from(name),
+                                            IpAddr::from_str(start).unwrap(),
+                                            IpAddr::from_str(end).unwrap());
+            ip.lookup = Some(String::from(lookup));
+            ip
+        };
+        let result = WhoisResult::new(vec![looked_up("DROPB", "DROPB", "162.125.0.0", "162.125.255.255"),
+                                           looked_up("DROPB", "DROPB", "199.47.216.0", "199.47.219.255"),
+                                           looked_up("GOGL", "GOOGLE", "8.8.8.0", "8.8.8.255")]);
+        let write = |format: OutputFormat| {
+            let mut out = Vec::new();
+            write_result(&result, format, &OutputOptions::default(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(write(OutputFormat::Plain),
+                   "# DROPB\n162.125.0.0/16\n199.47.216.0/22\n# GOGL\n8.8.8.0/24\n");
+        assert_eq!(write(OutputFormat::Csv(CsvDialect::default())),
+                   "lookup,name,start_ip,end_ip\nDROPB,DROPB,162.125.0.0,162.125.255.255\n\
+                    DROPB,DROPB,199.47.216.0,199.47.219.255\nGOGL,GOOGLE,8.8.8.0,8.8.8.255\n");
+        let ndjson = write(OutputFormat::Ndjson);
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines[2],
+                   "{\"name\":\"GOOGLE\",\"start_ip\":\"8.8.8.0\",\"end_ip\":\"8.8.8.255\",\"lookup\":\"GOGL\"}");
+    }
+
+    #[test]
+    fn write_result_csv() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB; \"EU\""),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap())]);
+        let write = |dialect: CsvDialect| {
+            let mut out = Vec::new();
+            write_result(&result, OutputFormat::Csv(dialect), &OutputOptions::default(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(write(CsvDialect::default()),
+                   "name,start_ip,end_ip\n\"DROPB; \"\"EU\"\"\",162.125.0.0,162.125.255.255\n");
+        let semicolon = CsvDialect {
+            delimiter: ';',
+            quote: CsvQuote::Minimal,
+        };
+        assert_eq!(write(semicolon),
+                   "name;start_ip;end_ip\n\"DROPB; \"\"EU\"\"\";162.125.0.0;162.125.255.255\n");
+        let always = CsvDialect {
+            delimiter: '\t',
+            quote: CsvQuote::Always,
+        };
+        assert_eq!(write(always),
+                   "\"name\"\t\"start_ip\"\t\"end_ip\"\n\"DROPB; \"\"EU\"\"\"\t\"162.125.0.0\"\t\"162.125.255.255\"\n");
+    }
+
+    #[test]
+    fn write_csv_ipv4_and_ipv6() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.255.255").unwrap()),
+                                           WhoisIpResult::new(String::from("DROPBOX-V6"),
+                                                              IpAddr::from_str("2620:0100:6000:0:0:0:0:0").unwrap(),
+                                                              IpAddr::from_str("2620:100:6000:ffff:ffff:ffff:ffff:ffff")
+                                                                  .unwrap())]);
+        let mut out = Vec::new();
+        write_csv(&result, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "name,start_ip,end_ip\n\
+                    DROPB,162.125.0.0,162.125.255.255\n\
+                    DROPBOX-V6,2620:100:6000::,2620:100:6000:ffff:ffff:ffff:ffff:ffff\n");
+    }
+
+    #[test]
+    fn write_csv_abuse_email() {
+        let mut with_email = WhoisIpResult::new(String::from("DROPB"),
+                                                IpAddr::from_str("162.125.0.0").unwrap(),
+                                                IpAddr::from_str("162.125.255.255").unwrap());
+        with_email.abuse_email = Some(String::from("abuse@dropbox.com"));
+        let without_email = WhoisIpResult::new(String::from("TEST"),
+                                               IpAddr::from_str("192.0.2.0").unwrap(),
+                                               IpAddr::from_str("192.0.2.255").unwrap());
+        let mut out = Vec::new();
+        write_csv(&WhoisResult::new(vec![with_email, without_email]), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "name,start_ip,end_ip,abuse_email\n\
+                    DROPB,162.125.0.0,162.125.255.255,abuse@dropbox.com\n\
+                    TEST,192.0.2.0,192.0.2.255,\n");
+    }
+
+    #[test]
+    fn csv_delimiter() {
+        assert_eq!(CsvDialect::parse_delimiter(";"), Ok(';'));
+        assert_eq!(CsvDialect::parse_delimiter("\\t"), Ok('\t'));
+        assert_eq!(CsvDialect::parse_delimiter("\t"), Ok('\t'));
+        assert!(CsvDialect::parse_delimiter("").is_err());
+        assert!(CsvDialect::parse_delimiter(";;").is_err());
+        assert!(CsvDialect::parse_delimiter("\"").is_err());
+        assert_eq!(CsvQuote::from_str("always"), Ok(CsvQuote::Always));
+        assert!(CsvQuote::from_str("never").is_err());
+    }
+
+    #[test]
+    fn to_ranges_json_cidrs() {
+        let result = WhoisResult::new(vec![WhoisIpResult::new(String::from("DROPB"),
+                                                              IpAddr::from_str("162.125.0.0").unwrap(),
+                                                              IpAddr::from_str("162.125.2.255").unwrap())]);
+        assert_eq!(to_ranges_json(&result, &[Field::Cidr]).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.2.255\",\
+                    \"cidrs\":[\"162.125.0.0/23\",\"162.125.2.0/24\"]}]");
+        assert_eq!(to_ranges_json(&result, &[]).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.2.255\"}]");
+    }
+
+    #[test]
+    fn to_ranges_json_dates() {
+        let mut result = dropbox_result();
+        result.ips[0].registration_date = Some(String::from("2013-06-26T14:26:06-04:00"));
+        result.ips[0].update_date = Some(String::from("2021-12-14T17:47:27-05:00"));
+        assert_eq!(to_ranges_json(&result, &[]).unwrap(),
+                   "[{\"name\":\"DROPB\",\"start_ip\":\"162.125.0.0\",\"end_ip\":\"162.125.255.255\",\
+                    \"registration_date\":\"2013-06-26T14:26:06-04:00\",\
+                    \"update_date\":\"2021-12-14T17:47:27-05:00\"}]");
+    }
+
+    #[test]
+    fn write_error_json_not_found() {
+        let err = WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound));
+        let mut out = Vec::new();
+        write_error(&err, None, OutputFormat::Json, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"error\":{\"kind\":\"not_found\",\"message\":\"HTTP Error: 404 Not Found\"}}\n");
+    }
+
+    #[test]
+    fn write_error_json_with_handle() {
+        let err = WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound));
+        let mut out = Vec::new();
+        write_error(&err, Some("NOSUCHORG"), OutputFormat::Json, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"error\":{\"handle\":\"NOSUCHORG\",\"kind\":\"not_found\",\"message\":\"HTTP Error: 404 \
+                    Not Found\"}}\n");
+    }
+
+    #[test]
+    fn write_pocs() {
+        let pocs = vec![PocLink {
+                            handle: String::from("NETWO7047-ARIN"),
+                            role: String::from("Abuse"),
+                        },
+                        PocLink {
+                            handle: String::from("DROPB1-ARIN"),
+                            role: String::from("Admin"),
+                        }];
+        let write = |format: OutputFormat| {
+            let mut out = Vec::new();
+            super::write_pocs("DROPB", &pocs, format, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(write(OutputFormat::Plain), "DROPB Abuse NETWO7047-ARIN\nDROPB Admin DROPB1-ARIN\n");
+        assert_eq!(write(OutputFormat::Json),
+                   "[{\"handle\":\"NETWO7047-ARIN\",\"role\":\"Abuse\"},\
+                    {\"handle\":\"DROPB1-ARIN\",\"role\":\"Admin\"}]\n");
+    }
+
+    #[test]
+    fn write_error_plain() {
+        let err = WhoisError::Http(HttpClientError::HttpError(StatusCode::NotFound));
+        let mut out = Vec::new();
+        write_error(&err, None, OutputFormat::Plain, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "HTTP Error: 404 Not Found\n");
+
+        let mut out = Vec::new();
+        write_error(&err, Some("NOSUCHORG"), OutputFormat::Plain, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "NOSUCHORG: HTTP Error: 404 Not Found\n");
+    }
+}
@@ -0,0 +1,378 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use super::WhoisIpResult;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    /// By start address, see `sort`
+    Start,
+    /// Largest ranges first, see `sort_by_size`
+    Size,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortOrder, String> {
+        match s {
+            "start" => Ok(SortOrder::Start),
+            "size" => Ok(SortOrder::Size),
+            _ => Err(format!("Unknown sort order: {}", s)),
+        }
+    }
+}
+
+/// What makes two ranges duplicates, for `--dedup-by`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupKey {
+    /// The same start and end, see `dedup`
+    Range,
+    /// The same ARIN handle, see `dedup_by_handle`
+    Handle,
+}
+
+impl FromStr for DedupKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DedupKey, String> {
+        match s {
+            "range" => Ok(DedupKey::Range),
+            "handle" => Ok(DedupKey::Handle),
+            _ => Err(format!("Unknown dedup key: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn of(ip: IpAddr) -> Family {
+        if ip.is_ipv4() { Family::V4 } else { Family::V6 }
+    }
+}
+
+impl FromStr for Family {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Family, String> {
+        match s {
+            "v4" => Ok(Family::V4),
+            "v6" => Ok(Family::V6),
+            _ => Err(format!("Unknown address family: {}", s)),
+        }
+    }
+}
+
+/// The address immediately before `ip`, or `None` if `ip` is the first
+/// address of its family.
+pub fn prev_addr(ip: IpAddr) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip).checked_sub(1).map(|n| IpAddr::V4(Ipv4Addr::from(n))),
+        IpAddr::V6(ip) => u128::from(ip).checked_sub(1).map(|n| IpAddr::V6(Ipv6Addr::from(n))),
+    }
+}
+
+/// The address immediately after `ip`, or `None` if `ip` is the last
+/// address of its family.
+pub fn next_addr(ip: IpAddr) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip).checked_add(1).map(|n| IpAddr::V4(Ipv4Addr::from(n))),
+        IpAddr::V6(ip) => u128::from(ip).checked_add(1).map(|n| IpAddr::V6(Ipv6Addr::from(n))),
+    }
+}
+
+/// Remove ranges with the same start and end as an earlier range.
+pub fn dedup(ips: &mut Vec<WhoisIpResult>) {
+    let mut seen = HashSet::new();
+    ips.retain(|ip| seen.insert((ip.start_ip, ip.end_ip)));
+}
+
+/// Remove ranges with the same start and end as another, keeping the one
+/// whose source comes first in `precedence`. It takes the place of the
+/// earliest of the duplicates.
+///
+/// Sources missing from `precedence`, and ranges without a source, lose to
+/// any listed source. Between equals the earlier range is kept, as by
+/// `dedup`.
+pub fn dedup_by_precedence(ips: &mut Vec<WhoisIpResult>, precedence: &[String]) {
+    let rank = |ip: &WhoisIpResult| {
+        ip.source
+            .as_ref()
+            .and_then(|source| precedence.iter().position(|name| name == source))
+            .unwrap_or(precedence.len())
+    };
+    let mut kept: Vec<WhoisIpResult> = Vec::with_capacity(ips.len());
+    let mut positions = HashMap::new();
+    for ip in ips.drain(..) {
+        if let Some(&i) = positions.get(&(ip.start_ip, ip.end_ip)) {
+            if rank(&ip) < rank(&kept[i]) {
+                kept[i] = ip;
+            }
+            continue;
+        }
+        positions.insert((ip.start_ip, ip.end_ip), kept.len());
+        kept.push(ip);
+    }
+    *ips = kept;
+}
+
+/// Remove ranges with the same handle as an earlier range, whatever their
+/// addresses. Ranges without a handle are compared as by `dedup`.
+pub fn dedup_by_handle(ips: &mut Vec<WhoisIpResult>) {
+    let mut seen_handles = HashSet::new();
+    let mut seen_ranges = HashSet::new();
+    ips.retain(|ip| match ip.handle {
+        Some(ref handle) => seen_handles.insert(handle.clone()),
+        None => seen_ranges.insert((ip.start_ip, ip.end_ip)),
+    });
+}
+
+/// Combine overlapping and adjacent ranges of the same family, returning the
+/// ranges sorted as by `sort`.
+///
+/// A combined range keeps the name of the range that starts it.
+pub fn merge(mut ips: Vec<WhoisIpResult>) -> Vec<WhoisIpResult> {
+    sort(&mut ips);
+    let mut merged: Vec<WhoisIpResult> = Vec::with_capacity(ips.len());
+    for ip in ips {
+        if let Some(last) = merged.last_mut() {
+            let touches = last.end_ip.is_ipv4() == ip.start_ip.is_ipv4() &&
+                          match next_addr(last.end_ip) {
+                Some(next) => ip.start_ip <= next,
+                None => true,
+            };
+            if touches {
+                if ip.end_ip > last.end_ip {
+                    last.end_ip = ip.end_ip;
+                }
+                continue;
+            }
+        }
+        merged.push(ip);
+    }
+    merged
+}
+
+/// Sort ranges by start address then end address.
+///
+/// IPv4 ranges sort before IPv6 ranges and addresses are compared
+/// numerically, so `10.0.0.0` comes before `162.125.0.0`.
+pub fn sort(ips: &mut [WhoisIpResult]) {
+    ips.sort_by_key(|ip| (ip.start_ip, ip.end_ip));
+}
+
+/// Sort ranges by descending `address_count`, breaking ties by start
+/// address so the order is deterministic.
+pub fn sort_by_size(ips: &mut [WhoisIpResult]) {
+    ips.sort_by_key(|ip| (Reverse(ip.address_count()), ip.start_ip, ip.end_ip));
+}
+
+/// Sort ranges in the given order.
+pub fn sort_in(ips: &mut [WhoisIpResult], order: SortOrder) {
+    match order {
+        SortOrder::Start => sort(ips),
+        SortOrder::Size => sort_by_size(ips),
+    }
+}
+
+/// Pick `count` ranges spread evenly across `ips`.
+///
+/// Every `(ips.len() / count)`th range is kept, starting with the first, so
+/// that a sorted input is sampled across its whole address space rather
+/// than just its beginning. If there are no more than `count` ranges all of
+/// them are kept.
+pub fn sample(ips: Vec<WhoisIpResult>, count: usize) -> Vec<WhoisIpResult> {
+    if count == 0 {
+        return Vec::new();
+    }
+    if ips.len() <= count {
+        return ips;
+    }
+    let stride = ips.len() / count;
+    ips.into_iter()
+        .step_by(stride)
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use super::{sort, sort_by_size, sample, prev_addr, next_addr, dedup, dedup_by_handle, dedup_by_precedence,
+                merge};
+    use super::super::WhoisIpResult;
+
+    fn ip_result(start: &str, end: &str) -> WhoisIpResult {
+        WhoisIpResult::new(String::from("TEST"),
+                           IpAddr::from_str(start).unwrap(),
+                           IpAddr::from_str(end).unwrap())
+    }
+
+    fn starts(ips: &[WhoisIpResult]) -> Vec<String> {
+        ips.iter().map(|ip| ip.start_ip.to_string()).collect()
+    }
+
+    fn ten_sorted() -> Vec<WhoisIpResult> {
+        (0..10)
+            .map(|i| ip_result(&format!("10.{}.0.0", i), &format!("10.{}.255.255", i)))
+            .collect()
+    }
+
+    #[test]
+    fn sort_numerically() {
+        let mut ips = vec![ip_result("2620:100::", "2620:100::ffff"),
+                           ip_result("162.125.0.0", "162.125.255.255"),
+                           ip_result("10.0.0.0", "10.0.0.255"),
+                           ip_result("9.0.0.0", "9.0.0.255")];
+        sort(&mut ips);
+        assert_eq!(starts(&ips),
+                   vec!["9.0.0.0", "10.0.0.0", "162.125.0.0", "2620:100::"]);
+    }
+
+    #[test]
+    fn sort_largest_first() {
+        let mut ips = vec![ip_result("10.0.0.0", "10.0.0.255"),
+                           ip_result("2620:100::", "2620:100::ffff"),
+                           ip_result("162.125.0.0", "162.125.255.255"),
+                           ip_result("9.0.0.0", "9.0.0.255"),
+                           ip_result("8.8.8.8", "8.8.8.8")];
+        sort_by_size(&mut ips);
+        assert_eq!(starts(&ips),
+                   vec!["162.125.0.0", "2620:100::", "9.0.0.0", "10.0.0.0", "8.8.8.8"]);
+    }
+
+    #[test]
+    fn sample_stride() {
+        let sampled = sample(ten_sorted(), 3);
+        assert_eq!(starts(&sampled), vec!["10.0.0.0", "10.3.0.0", "10.6.0.0"]);
+
+        let sampled = sample(ten_sorted(), 5);
+        assert_eq!(starts(&sampled),
+                   vec!["10.0.0.0", "10.2.0.0", "10.4.0.0", "10.6.0.0", "10.8.0.0"]);
+    }
+
+    #[test]
+    fn sample_more_than_available() {
+        assert_eq!(sample(ten_sorted(), 10).len(), 10);
+        assert_eq!(sample(ten_sorted(), 20).len(), 10);
+        assert_eq!(sample(ten_sorted(), 0).len(), 0);
+    }
+
+    #[test]
+    fn prev_addr_wraps_octets() {
+        let ip = |s: &str| IpAddr::from_str(s).unwrap();
+        assert_eq!(prev_addr(ip("10.0.1.0")), Some(ip("10.0.0.255")));
+        assert_eq!(prev_addr(ip("0.0.0.0")), None);
+        assert_eq!(prev_addr(ip("2620:100::")), Some(ip("2620:ff:ffff:ffff:ffff:ffff:ffff:ffff")));
+    }
+
+    #[test]
+    fn next_addr_wraps_octets() {
+        let ip = |s: &str| IpAddr::from_str(s).unwrap();
+        assert_eq!(next_addr(ip("10.0.0.255")), Some(ip("10.0.1.0")));
+        assert_eq!(next_addr(ip("255.255.255.255")), None);
+        assert_eq!(next_addr(ip("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")), None);
+    }
+
+    #[test]
+    fn dedup_keeps_first() {
+        let mut ips = vec![ip_result("10.0.0.0", "10.0.0.255"),
+                           ip_result("10.0.0.0", "10.0.1.255"),
+                           ip_result("10.0.0.0", "10.0.0.255")];
+        dedup(&mut ips);
+        assert_eq!(ips.len(), 2);
+        assert_eq!(ips[1].end_ip, IpAddr::from_str("10.0.1.255").unwrap());
+    }
+
+    #[test]
+    fn dedup_duplicated_dropbox_range() {
+        // ARIN lists this range twice for DROPB, see parse_content_ipv4_and_ipv6
+        let mut ips = vec![ip_result("162.125.0.0", "162.125.255.255"),
+                           ip_result("192.0.2.0", "192.0.2.255"),
+                           ip_result("162.125.0.0", "162.125.255.255")];
+        dedup(&mut ips);
+        assert_eq!(starts(&ips), vec!["162.125.0.0", "192.0.2.0"]);
+    }
+
+    #[test]
+    fn dedup_by_handle_keeps_first() {
+        let mut first = ip_result("162.125.0.0", "162.125.255.255");
+        first.handle = Some(String::from("NET-162-125-0-0-1"));
+        let mut same_handle = ip_result("::ffff:162.125.0.0", "::ffff:162.125.255.255");
+        same_handle.handle = Some(String::from("NET-162-125-0-0-1"));
+        let mut ips = vec![first,
+                           same_handle,
+                           ip_result("10.0.0.0", "10.0.0.255"),
+                           ip_result("10.0.0.0", "10.0.0.255")];
+        dedup_by_handle(&mut ips);
+        assert_eq!(starts(&ips), vec!["162.125.0.0", "10.0.0.0"]);
+    }
+
+    #[test]
+    fn dedup_by_precedence_keeps_preferred_source() {
+        let from = |name: &str, source: Option<&str>| {
+            let mut ip = WhoisIpResult::new(String::from(name),
+                                            IpAddr::from_str("193.0.0.0").unwrap(),
+                                            IpAddr::from_str("193.0.7.255").unwrap());
+            ip.source = source.map(String::from);
+            ip
+        };
+        let ips = vec![from("ARIN-MIRROR", Some("arin")),
+                       ip_result("10.0.0.0", "10.0.0.255"),
+                       from("RIPE-NCC", Some("ripe")),
+                       from("UNLABELLED", None)];
+        let precedence = vec![String::from("ripe"), String::from("arin")];
+
+        let mut preferred = ips.clone();
+        dedup_by_precedence(&mut preferred, &precedence);
+        let names: Vec<&str> = preferred.iter().map(|ip| ip.name.as_ref()).collect();
+        assert_eq!(names, vec!["RIPE-NCC", "TEST"]);
+
+        let mut first_wins = ips.clone();
+        dedup_by_precedence(&mut first_wins, &[]);
+        assert_eq!(first_wins[0].name, "ARIN-MIRROR");
+        assert_eq!(first_wins.len(), 2);
+    }
+
+    #[test]
+    fn merge_overlapping_and_adjacent() {
+        let merged = merge(vec![ip_result("10.0.2.0", "10.0.2.255"),
+                                ip_result("10.0.0.0", "10.0.0.255"),
+                                ip_result("10.0.0.128", "10.0.1.255"),
+                                ip_result("10.0.4.0", "10.0.4.255"),
+                                ip_result("255.255.255.0", "255.255.255.255"),
+                                ip_result("::", "::ff")]);
+        let bounds: Vec<(String, String)> = merged.iter()
+            .map(|ip| (ip.start_ip.to_string(), ip.end_ip.to_string()))
+            .collect();
+        assert_eq!(bounds,
+                   vec![(String::from("10.0.0.0"), String::from("10.0.2.255")),
+                        (String::from("10.0.4.0"), String::from("10.0.4.255")),
+                        (String::from("255.255.255.0"), String::from("255.255.255.255")),
+                        (String::from("::"), String::from("::ff"))]);
+    }
+
+    #[test]
+    fn merge_adjacent_ipv6() {
+        let merged = merge(vec![ip_result("2001:db8::100", "2001:db8::1ff"), ip_result("2001:db8::", "2001:db8::ff")]);
+        assert_eq!(starts(&merged), vec!["2001:db8::"]);
+        assert_eq!(merged[0].end_ip.to_string(), "2001:db8::1ff");
+    }
+
+    #[test]
+    fn merge_nothing_to_merge() {
+        // A gap of a single address keeps ranges apart
+        let merged = merge(vec![ip_result("10.0.1.0", "10.0.1.255"), ip_result("10.0.0.0", "10.0.0.254")]);
+        assert_eq!(starts(&merged), vec!["10.0.0.0", "10.0.1.0"]);
+        assert_eq!(merge(Vec::new()).len(), 0);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io;
+use std::str::FromStr;
+
+use serde_json::{self, Value};
+
+use super::{WhoisResult, WhoisIpResult};
+use cidr::IpNet;
+use xmlparser::{WhoisXmlParser, StdWhoisXmlParser, NetDetails, ParseError, PocLink};
+
+/// Parses the RDAP (RFC 9083) entity APNIC, LACNIC and AFRINIC answer an
+/// org handle with, whose `networks` are the nets registered to it:
+///
+/// ```text
+/// {"objectClassName": "entity", "handle": "ORG-AFNC1-AFRINIC",
+///  "networks": [{"objectClassName": "ip network", "handle": "196.216.2.0 - 196.216.3.255",
+///                "startAddress": "196.216.2.0", "endAddress": "196.216.3.255",
+///                "ipVersion": "v4", "name": "AFRINIC-NET"}]}
+/// ```
+///
+/// The registries fill networks in differently: LACNIC leaves out `name`
+/// and uses the prefix as the handle, so the name falls back to the
+/// handle, and a network without `endAddress` is read from its
+/// `cidr0_cidrs` prefixes instead.
+#[derive(Debug, Default)]
+pub struct RdapJsonParser;
+
+impl RdapJsonParser {
+    pub fn new() -> RdapJsonParser {
+        RdapJsonParser
+    }
+
+    /// The ranges of one `ip network`, one per `cidr0_cidrs` prefix if it
+    /// has no `startAddress` and `endAddress`.
+    fn parse_network(network: &Value) -> Result<Vec<WhoisIpResult>, ParseError> {
+        let field = |name: &str| network.get(name).and_then(Value::as_str);
+        let name = match field("name").or_else(|| field("handle")) {
+            Some(name) => name.trim().to_string(),
+            None => return Err(ParseError::JsonError(String::from("Network has no name or handle"))),
+        };
+        let ranges = match (field("startAddress"), field("endAddress")) {
+            (Some(start_ip), Some(end_ip)) => {
+                vec![(StdWhoisXmlParser::parse_ip(start_ip)?, StdWhoisXmlParser::parse_ip(end_ip)?)]
+            }
+            _ => {
+                let cidrs = network.get("cidr0_cidrs").and_then(Value::as_array).map_or(&[][..], |cidrs| cidrs);
+                if cidrs.is_empty() {
+                    return Err(ParseError::JsonError(format!("Network {} has no addresses", name)));
+                }
+                let mut ranges = Vec::new();
+                for cidr in cidrs {
+                    let prefix = cidr.get("v4prefix").or_else(|| cidr.get("v6prefix")).and_then(Value::as_str);
+                    let net = match (prefix, cidr.get("length").and_then(Value::as_u64)) {
+                        (Some(prefix), Some(length)) => {
+                            IpNet::from_str(&format!("{}/{}", prefix.trim(), length)).map_err(ParseError::IpAddrError)?
+                        }
+                        _ => return Err(ParseError::JsonError(format!("Network {} has an invalid cidr0_cidrs", name))),
+                    };
+                    ranges.push((net.addr(), net.last_addr()));
+                }
+                ranges
+            }
+        };
+        let handle = field("handle").map(|handle| handle.trim().to_string());
+        Ok(ranges.into_iter()
+            .map(|(start_ip, end_ip)| {
+                let mut ip = WhoisIpResult::new(name.clone(), start_ip, end_ip);
+                ip.handle = handle.clone();
+                ip
+            })
+            .collect())
+    }
+}
+
+impl WhoisXmlParser for RdapJsonParser {
+    fn parse_content<T: io::Read>(&self, json: T) -> Result<WhoisResult, ParseError> {
+        let value: Value = serde_json::from_reader(json).map_err(|e| ParseError::JsonError(e.to_string()))?;
+        if value.get("objectClassName").and_then(Value::as_str) != Some("entity") {
+            return Err(ParseError::JsonError(String::from("Expected an RDAP entity")));
+        }
+        let org = value.get("handle").and_then(Value::as_str).map(String::from);
+        let mut ips = Vec::new();
+        if let Some(networks) = value.get("networks").and_then(Value::as_array) {
+            for network in networks {
+                for mut ip in RdapJsonParser::parse_network(network)? {
+                    ip.org = org.clone();
+                    ips.push(ip);
+                }
+            }
+        }
+        Ok(WhoisResult::new(ips))
+    }
+
+    fn parse_net_details<T: io::Read>(&self, _json: T) -> Result<NetDetails, ParseError> {
+        Err(ParseError::Unsupported(String::from("Net details are only looked up in ARIN")))
+    }
+
+    fn parse_pocs<T: io::Read>(&self, _json: T) -> Result<Vec<PocLink>, ParseError> {
+        Err(ParseError::Unsupported(String::from("Points of contact are only looked up in ARIN")))
+    }
+
+    fn parse_poc_emails<T: io::Read>(&self, _json: T) -> Result<Vec<String>, ParseError> {
+        Err(ParseError::Unsupported(String::from("Points of contact are only looked up in ARIN")))
+    }
+
+    fn parse_asn_org<T: io::Read>(&self, _json: T) -> Result<String, ParseError> {
+        Err(ParseError::Unsupported(String::from("AS numbers are only looked up in ARIN")))
+    }
+
+    fn accept(&self) -> &'static str {
+        "application/rdap+json"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RdapJsonParser;
+    use xmlparser::{WhoisXmlParser, ParseError};
+
+    fn ranges(json: &str) -> Vec<(String, String, String)> {
+        RdapJsonParser::new()
+            .parse_content(json.as_bytes())
+            .unwrap()
+            .ips
+            .iter()
+            .map(|ip| (ip.name.clone(), ip.start_ip.to_string(), ip.end_ip.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_apnic_entity() {
+        let json = r#"{
+  "rdapConformance": ["rdap_level_0", "cidr0"],
+  "objectClassName": "entity",
+  "handle": "ORG-APNIC-AP",
+  "roles": ["registrant"],
+  "networks": [
+    {"objectClassName": "ip network", "handle": "203.119.101.0 - 203.119.101.255",
+     "startAddress": "203.119.101.0", "endAddress": "203.119.101.255", "ipVersion": "v4",
+     "name": "APNIC-SERVICES", "type": "ASSIGNED PORTABLE", "country": "AU",
+     "cidr0_cidrs": [{"v4prefix": "203.119.101.0", "length": 24}]},
+    {"objectClassName": "ip network", "handle": "2001:dc0:2000::/35",
+     "startAddress": "2001:dc0:2000::", "endAddress": "2001:dc0:3fff:ffff:ffff:ffff:ffff:ffff",
+     "ipVersion": "v6", "name": "APNIC-AP-V6"}
+  ]
+}"#;
+        assert_eq!(ranges(json),
+                   vec![(String::from("APNIC-SERVICES"),
+                         String::from("203.119.101.0"),
+                         String::from("203.119.101.255")),
+                        (String::from("APNIC-AP-V6"),
+                         String::from("2001:dc0:2000::"),
+                         String::from("2001:dc0:3fff:ffff:ffff:ffff:ffff:ffff"))]);
+        let result = RdapJsonParser::new().parse_content(json.as_bytes()).unwrap();
+        assert_eq!(result.ips[0].handle, Some(String::from("203.119.101.0 - 203.119.101.255")));
+        assert_eq!(result.ips[0].org, Some(String::from("ORG-APNIC-AP")));
+    }
+
+    #[test]
+    fn parse_lacnic_entity() {
+        let json = r#"{
+  "rdapConformance": ["rdap_level_0", "cidr0"],
+  "objectClassName": "entity",
+  "handle": "UY-LACN-LACNIC",
+  "networks": [
+    {"objectClassName": "ip network", "handle": "200.3.12.0/22",
+     "startAddress": "200.3.12.0", "endAddress": "200.3.15.255", "ipVersion": "v4"},
+    {"objectClassName": "ip network", "handle": "2001:13c7:7000::/36", "ipVersion": "v6",
+     "cidr0_cidrs": [{"v6prefix": "2001:13c7:7000::", "length": 36}]}
+  ]
+}"#;
+        assert_eq!(ranges(json),
+                   vec![(String::from("200.3.12.0/22"), String::from("200.3.12.0"), String::from("200.3.15.255")),
+                        (String::from("2001:13c7:7000::/36"),
+                         String::from("2001:13c7:7000::"),
+                         String::from("2001:13c7:7fff:ffff:ffff:ffff:ffff:ffff"))]);
+    }
+
+    #[test]
+    fn parse_afrinic_entity() {
+        let json = r#"{
+  "rdapConformance": ["rdap_level_0"],
+  "objectClassName": "entity",
+  "handle": "ORG-AFNC1-AFRINIC",
+  "networks": [
+    {"objectClassName": "ip network", "handle": "196.216.2.0 - 196.216.3.255",
+     "startAddress": "196.216.2.0", "endAddress": "196.216.3.255", "ipVersion": "v4",
+     "name": "AFRINIC-NET", "country": "MU"}
+  ]
+}"#;
+        assert_eq!(ranges(json),
+                   vec![(String::from("AFRINIC-NET"), String::from("196.216.2.0"), String::from("196.216.3.255"))]);
+        let no_networks = r#"{"objectClassName": "entity", "handle": "ORG-EMPTY-AFRINIC"}"#;
+        assert!(ranges(no_networks).is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_entity() {
+        let invalid = ["",
+                       r#"{"objectClassName": "ip network", "handle": "196.216.2.0 - 196.216.3.255"}"#,
+                       r#"{"objectClassName": "entity", "networks": [{"handle": "200.3.12.0/22"}]}"#,
+                       r#"{"objectClassName": "entity", "networks": [{"startAddress": "200.3.12.0"}]}"#];
+        for json in &invalid {
+            match RdapJsonParser::new().parse_content(json.as_bytes()) {
+                Err(ParseError::JsonError(_)) => {}
+                other => panic!("Expected a JSON error for {:?}, got {:?}", json, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use super::ARIN_REST_URL;
+
+/// Base of the RIPE database's REST API.
+pub const RIPE_REST_URL: &str = "https://rest.db.ripe.net";
+
+/// Base of APNIC's RDAP service.
+pub const APNIC_RDAP_URL: &str = "https://rdap.apnic.net";
+
+/// Base of LACNIC's RDAP service.
+pub const LACNIC_RDAP_URL: &str = "https://rdap.lacnic.net/rdap";
+
+/// Base of AFRINIC's RDAP service.
+pub const AFRINIC_RDAP_URL: &str = "https://rdap.afrinic.net/rdap";
+
+/// The regional Internet registry companies are looked up in, chosen with
+/// `--registry`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Registry {
+    /// North America, see `StdWhoisXmlParser` and `JsonWhoisParser`
+    Arin,
+    /// Europe, the Middle East and parts of Central Asia, see `RipeXmlParser`
+    Ripe,
+    /// Asia and the Pacific, see `RdapJsonParser`
+    Apnic,
+    /// Latin America and the Caribbean, see `RdapJsonParser`
+    Lacnic,
+    /// Africa, see `RdapJsonParser`
+    Afrinic,
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::Arin
+    }
+}
+
+impl FromStr for Registry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Registry, String> {
+        match s {
+            "arin" => Ok(Registry::Arin),
+            "ripe" => Ok(Registry::Ripe),
+            "apnic" => Ok(Registry::Apnic),
+            "lacnic" => Ok(Registry::Lacnic),
+            "afrinic" => Ok(Registry::Afrinic),
+            _ => Err(format!("Unknown registry: {}", s)),
+        }
+    }
+}
+
+impl Registry {
+    /// Short lowercase name, as used by `WhoisSource::name`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Registry::Arin => "arin",
+            Registry::Ripe => "ripe",
+            Registry::Apnic => "apnic",
+            Registry::Lacnic => "lacnic",
+            Registry::Afrinic => "afrinic",
+        }
+    }
+
+    /// The registry ARIN refers lookups to with the org handle `handle`, one
+    /// of `source::REFERRAL_HANDLES`, e.g. `Ripe` for `RIPE`.
+    pub fn from_referral(handle: &str) -> Option<Registry> {
+        match handle {
+            "RIPE" => Some(Registry::Ripe),
+            "APNIC" => Some(Registry::Apnic),
+            "LACNIC" => Some(Registry::Lacnic),
+            "AFRINIC" => Some(Registry::Afrinic),
+            _ => None,
+        }
+    }
+
+    /// The URL listing the nets registered to the org `handle`, e.g.
+    /// `DROPB` for ARIN or `ORG-RIEN1-RIPE` for RIPE. The RDAP registries
+    /// answer with the org's entity, which lists its networks.
+    pub fn org_nets_url(&self, handle: &str) -> String {
+        match *self {
+            Registry::Arin => format!("{}/org/{}/nets", ARIN_REST_URL, handle),
+            Registry::Ripe => {
+                format!("{}/search.xml?query-string={}&inverse-attribute=org&type-filter=inetnum&\
+                         type-filter=inet6num&flags=no-referenced",
+                        RIPE_REST_URL,
+                        handle)
+            }
+            Registry::Apnic => format!("{}/entity/{}", APNIC_RDAP_URL, handle),
+            Registry::Lacnic => format!("{}/entity/{}", LACNIC_RDAP_URL, handle),
+            Registry::Afrinic => format!("{}/entity/{}", AFRINIC_RDAP_URL, handle),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::Registry;
+    use source::REFERRAL_HANDLES;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Registry::from_str("arin"), Ok(Registry::Arin));
+        assert_eq!(Registry::from_str("ripe"), Ok(Registry::Ripe));
+        assert_eq!(Registry::from_str("apnic"), Ok(Registry::Apnic));
+        assert_eq!(Registry::from_str("lacnic"), Ok(Registry::Lacnic));
+        assert_eq!(Registry::from_str("afrinic"), Ok(Registry::Afrinic));
+        assert!(Registry::from_str("iana").is_err());
+    }
+
+    #[test]
+    fn from_referral() {
+        for handle in &REFERRAL_HANDLES {
+            assert_eq!(Registry::from_referral(handle).map(|registry| registry.name().to_uppercase()),
+                       Some(handle.to_string()));
+        }
+        assert_eq!(Registry::from_referral("ARIN"), None);
+        assert_eq!(Registry::from_referral("ripe"), None);
+    }
+
+    #[test]
+    fn org_nets_url() {
+        assert_eq!(Registry::Arin.org_nets_url("DROPB"), "https://whois.arin.net/rest/org/DROPB/nets");
+        assert_eq!(Registry::Ripe.org_nets_url("ORG-RIEN1-RIPE"),
+                   "https://rest.db.ripe.net/search.xml?query-string=ORG-RIEN1-RIPE&inverse-attribute=org&\
+                    type-filter=inetnum&type-filter=inet6num&flags=no-referenced");
+        assert_eq!(Registry::Lacnic.org_nets_url("UY-LACN-LACNIC"),
+                   "https://rdap.lacnic.net/rdap/entity/UY-LACN-LACNIC");
+        assert_eq!(Registry::Afrinic.org_nets_url("ORG-AFNC1-AFRINIC"),
+                   "https://rdap.afrinic.net/rdap/entity/ORG-AFNC1-AFRINIC");
+    }
+}
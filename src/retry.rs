@@ -0,0 +1,234 @@
+use std::cmp;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use httpclient::{WhoisHttpClient, HttpClientError, FetchMeta};
+
+/// Decides whether a failed request is tried again, and after how long.
+pub trait RetryPolicy {
+    /// The wait before trying again after `err`, where `attempt` is the
+    /// number of retries already made, or `None` to give up with `err`.
+    fn next_delay(&self, attempt: u32, err: &HttpClientError) -> Option<Duration>;
+}
+
+/// The longest `ExponentialBackoff` waits between two attempts, however many
+/// it has already made.
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Retries retryable errors up to `retries` times.
+///
+/// The delay before each retry is the server's `Retry-After` if it gave
+/// one. Otherwise it is `delay` doubled for every attempt already made, up
+/// to `MAX_RETRY_DELAY`, of which a random part of up to half is left out
+/// so that clients that failed together do not all come back together.
+#[derive(Debug)]
+pub struct ExponentialBackoff {
+    retries: u32,
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl ExponentialBackoff {
+    pub fn new(retries: u32) -> ExponentialBackoff {
+        ExponentialBackoff {
+            retries: retries,
+            delay: Duration::from_secs(1),
+            max_delay: MAX_RETRY_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32, err: &HttpClientError) -> Option<Duration> {
+        if !err.is_retryable() || attempt >= self.retries {
+            return None;
+        }
+        if let Some(retry_after) = err.retry_after() {
+            return Some(retry_after);
+        }
+        let backoff = 2u32.checked_pow(attempt)
+            .and_then(|factor| self.delay.checked_mul(factor))
+            .map_or(self.max_delay, |backoff| cmp::min(backoff, self.max_delay));
+        Some(backoff / 2 + jitter(backoff / 2))
+    }
+}
+
+/// A duration from zero up to `max`, random enough to spread out retries.
+fn jitter(max: Duration) -> Duration {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.subsec_nanos()).unwrap_or(0);
+    max * (nanos % 1000) / 1000
+}
+
+/// Wraps a `WhoisHttpClient`, repeating requests that fail for as long as
+/// its `RetryPolicy` says to.
+#[derive(Debug)]
+pub struct RetryingWhoisHttpClient<C: WhoisHttpClient, P: RetryPolicy = ExponentialBackoff> {
+    client: C,
+    policy: P,
+}
+
+impl<C: WhoisHttpClient, P: RetryPolicy> RetryingWhoisHttpClient<C, P> {
+    pub fn new(client: C, policy: P) -> RetryingWhoisHttpClient<C, P> {
+        RetryingWhoisHttpClient {
+            client: client,
+            policy: policy,
+        }
+    }
+}
+
+impl<C: WhoisHttpClient, P: RetryPolicy> WhoisHttpClient for RetryingWhoisHttpClient<C, P> {
+    fn get_content(&self, url: &str) -> Result<String, HttpClientError> {
+        self.fetch(url).map(|(body, _)| body)
+    }
+
+    fn fetch(&self, url: &str) -> Result<(String, FetchMeta), HttpClientError> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.client.fetch(url) {
+                Err(err) => err,
+                Ok((body, mut meta)) => {
+                    meta.retry_count += attempt;
+                    return Ok((body, meta));
+                }
+            };
+            match self.policy.next_delay(attempt, &err) {
+                Some(delay) => thread::sleep(delay),
+                None => return Err(err),
+            }
+            attempt += 1;
+        }
+    }
+
+    // A POST is not necessarily safe to repeat, so it is never retried
+    fn post_content(&self, url: &str, body: &str, content_type: &str) -> Result<String, HttpClientError> {
+        self.client.post_content(url, body, content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    use hyper::status::StatusCode;
+
+    use super::{ExponentialBackoff, RetryPolicy, RetryingWhoisHttpClient, MAX_RETRY_DELAY};
+    use httpclient::{WhoisHttpClient, HttpClientError};
+
+    /// Fails with 503 the first `failures` times it is asked.
+    struct MaintenanceHttpClient {
+        failures: u32,
+        calls: Cell<u32>,
+    }
+
+    impl WhoisHttpClient for MaintenanceHttpClient {
+        fn get_content(&self, _url: &str) -> Result<String, HttpClientError> {
+            self.calls.set(self.calls.get() + 1);
+            if self.calls.get() <= self.failures {
+                Err(HttpClientError::from_status(StatusCode::ServiceUnavailable, Some("0")))
+            } else {
+                Ok(String::from("<nets/>"))
+            }
+        }
+    }
+
+    fn maintenance(failures: u32) -> MaintenanceHttpClient {
+        MaintenanceHttpClient {
+            failures: failures,
+            calls: Cell::new(0),
+        }
+    }
+
+    fn retrying(failures: u32, retries: u32) -> RetryingWhoisHttpClient<MaintenanceHttpClient> {
+        let mut policy = ExponentialBackoff::new(retries);
+        policy.delay = Duration::from_millis(1);
+        RetryingWhoisHttpClient::new(maintenance(failures), policy)
+    }
+
+    #[test]
+    fn retries_service_unavailable() {
+        let client = retrying(2, 3);
+        assert_eq!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").unwrap(), "<nets/>");
+        assert_eq!(client.client.calls.get(), 3);
+    }
+
+    #[test]
+    fn counts_retries() {
+        let client = retrying(2, 3);
+        let (_, meta) = client.fetch("https://whois.arin.net/rest/org/DROPB/nets").unwrap();
+        assert_eq!(meta.retry_count, 2);
+
+        let (_, meta) = retrying(0, 3).fetch("https://whois.arin.net/rest/org/DROPB/nets").unwrap();
+        assert_eq!(meta.retry_count, 0);
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let client = retrying(5, 2);
+        match client.get_content("https://whois.arin.net/rest/org/DROPB/nets") {
+            Err(HttpClientError::ServiceUnavailable { retry_after }) => assert_eq!(retry_after, Some(Duration::from_secs(0))),
+            other => panic!("Expected ServiceUnavailable, got {:?}", other),
+        }
+        assert_eq!(client.client.calls.get(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_not_found() {
+        struct NotFoundHttpClient;
+        impl WhoisHttpClient for NotFoundHttpClient {
+            fn get_content(&self, _url: &str) -> Result<String, HttpClientError> {
+                Err(HttpClientError::HttpError(StatusCode::NotFound))
+            }
+        }
+        let client = RetryingWhoisHttpClient::new(NotFoundHttpClient, ExponentialBackoff::new(3));
+        assert!(client.get_content("https://whois.arin.net/rest/org/NOSUCHORG/nets").is_err());
+    }
+
+    #[test]
+    fn exponential_backoff_delays() {
+        let policy = ExponentialBackoff::new(3);
+        let unavailable = HttpClientError::from_status(StatusCode::ServiceUnavailable, None);
+        for attempt in 0..3 {
+            let delay = policy.next_delay(attempt, &unavailable).unwrap();
+            let backoff = Duration::from_secs(1) * 2u32.pow(attempt);
+            assert!(delay >= backoff / 2 && delay <= backoff, "{:?} for attempt {}", delay, attempt);
+        }
+        assert_eq!(policy.next_delay(3, &unavailable), None);
+
+        let retry_after = HttpClientError::from_status(StatusCode::ServiceUnavailable, Some("7"));
+        assert_eq!(policy.next_delay(0, &retry_after), Some(Duration::from_secs(7)));
+        let rate_limited = HttpClientError::from_status(StatusCode::TooManyRequests, Some("5"));
+        assert_eq!(policy.next_delay(0, &rate_limited), Some(Duration::from_secs(5)));
+        assert_eq!(policy.next_delay(0, &HttpClientError::HttpError(StatusCode::NotFound)), None);
+    }
+
+    #[test]
+    fn exponential_backoff_capped() {
+        let policy = ExponentialBackoff::new(100);
+        let unavailable = HttpClientError::from_status(StatusCode::ServiceUnavailable, None);
+        for &attempt in &[6, 20, 31, 32, 99] {
+            let delay = policy.next_delay(attempt, &unavailable).unwrap();
+            assert!(delay >= MAX_RETRY_DELAY / 2 && delay <= MAX_RETRY_DELAY,
+                    "{:?} for attempt {}",
+                    delay,
+                    attempt);
+        }
+    }
+
+    #[test]
+    fn honors_custom_policy() {
+        /// Waits the given delays in turn, retrying any error, then gives up.
+        struct FixedDelays(Vec<Duration>);
+        impl RetryPolicy for FixedDelays {
+            fn next_delay(&self, attempt: u32, _err: &HttpClientError) -> Option<Duration> {
+                self.0.get(attempt as usize).cloned()
+            }
+        }
+        let policy = FixedDelays(vec![Duration::from_millis(20), Duration::from_millis(30)]);
+        let client = RetryingWhoisHttpClient::new(maintenance(5), policy);
+        let started = Instant::now();
+        assert!(client.get_content("https://whois.arin.net/rest/org/DROPB/nets").is_err());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(client.client.calls.get(), 3);
+    }
+}
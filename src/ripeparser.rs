@@ -0,0 +1,191 @@
+use std::io;
+use std::str::FromStr;
+
+use xml::reader::{EventReader, XmlEvent};
+
+use super::{WhoisResult, WhoisIpResult};
+use cidr::IpNet;
+use xmlparser::{WhoisXmlParser, StdWhoisXmlParser, NetDetails, ParseError, PocLink};
+
+/// Parses the XML of the RIPE database's REST API, whose `search.xml`
+/// answers hold an `object` per matching record:
+///
+/// ```text
+/// <object type="inetnum">
+///   <attributes>
+///     <attribute name="inetnum" value="193.0.0.0 - 193.0.7.255"/>
+///     <attribute name="netname" value="RIPE-NCC"/>
+///     <attribute name="org" value="ORG-RIEN1-RIPE"/>
+///   </attributes>
+/// </object>
+/// ```
+///
+/// Only `inetnum` and `inet6num` objects are nets, the latter holding a
+/// CIDR prefix rather than a range.
+#[derive(Debug, Default)]
+pub struct RipeXmlParser;
+
+impl RipeXmlParser {
+    pub fn new() -> RipeXmlParser {
+        RipeXmlParser
+    }
+
+    /// The net an object describes, if it is an `inetnum` or `inet6num`.
+    fn parse_object(object_type: &str, attributes: &[(String, String)]) -> Result<Option<WhoisIpResult>, ParseError> {
+        let value = |key: &str| attributes.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| v.as_str());
+        let range = match value(object_type) {
+            Some(range) if object_type == "inetnum" || object_type == "inet6num" => range,
+            _ => return Ok(None),
+        };
+        let (start_ip, end_ip) = if object_type == "inet6num" {
+            let net = IpNet::from_str(range.trim()).map_err(ParseError::IpAddrError)?;
+            (net.addr(), net.last_addr())
+        } else {
+            let mut bounds = range.splitn(2, '-').map(|addr| addr.trim());
+            match (bounds.next(), bounds.next()) {
+                (Some(start), Some(end)) => (StdWhoisXmlParser::parse_ip(start)?, StdWhoisXmlParser::parse_ip(end)?),
+                _ => return Err(ParseError::XmlError(format!("Invalid inetnum: {}", range))),
+            }
+        };
+        let name = value("netname").unwrap_or(range);
+        let mut ip = WhoisIpResult::new(name.to_string(), start_ip, end_ip);
+        ip.org = value("org").map(String::from);
+        Ok(Some(ip))
+    }
+}
+
+impl WhoisXmlParser for RipeXmlParser {
+    fn parse_content<T: io::Read>(&self, xml: T) -> Result<WhoisResult, ParseError> {
+        let mut ips = Vec::new();
+        // The type and attributes of the object being parsed
+        let mut object: Option<(String, Vec<(String, String)>)> = None;
+        for elm in EventReader::new(xml) {
+            match elm {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    let attribute = |name: &str| {
+                        attributes.iter().find(|a| a.name.local_name == name).map(|a| a.value.clone())
+                    };
+                    match name.local_name.as_ref() {
+                        "object" => object = Some((attribute("type").unwrap_or_default(), Vec::new())),
+                        "attribute" => {
+                            if let Some((_, ref mut object_attributes)) = object {
+                                if let (Some(name), Some(value)) = (attribute("name"), attribute("value")) {
+                                    object_attributes.push((name, value));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(XmlEvent::EndElement { name }) => {
+                    if name.local_name == "object" {
+                        if let Some((object_type, attributes)) = object.take() {
+                            if let Some(ip) = RipeXmlParser::parse_object(&object_type, &attributes)? {
+                                ips.push(ip);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(ParseError::from(e));
+                }
+                _ => {}
+            }
+        }
+        Ok(WhoisResult::new(ips))
+    }
+
+    fn parse_net_details<T: io::Read>(&self, _xml: T) -> Result<NetDetails, ParseError> {
+        Err(ParseError::Unsupported(String::from("RIPE nets have no separate details record")))
+    }
+
+    fn parse_pocs<T: io::Read>(&self, _xml: T) -> Result<Vec<PocLink>, ParseError> {
+        Err(ParseError::Unsupported(String::from("RIPE orgs have no points of contact list")))
+    }
+
+    fn parse_poc_emails<T: io::Read>(&self, _xml: T) -> Result<Vec<String>, ParseError> {
+        Err(ParseError::Unsupported(String::from("RIPE orgs have no points of contact list")))
+    }
+
+    fn parse_asn_org<T: io::Read>(&self, _xml: T) -> Result<String, ParseError> {
+        Err(ParseError::Unsupported(String::from("AS numbers are only looked up in ARIN")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use super::RipeXmlParser;
+    use xmlparser::{WhoisXmlParser, ParseError};
+
+    const RIPE_NCC: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<whois-resources xmlns:xlink="http://www.w3.org/1999/xlink">
+  <service name="search"/>
+  <objects>
+    <object type="inetnum">
+      <link xlink:type="locator" xlink:href="https://rest.db.ripe.net/ripe/inetnum/193.0.0.0 - 193.0.7.255"/>
+      <source id="ripe"/>
+      <primary-key>
+        <attribute name="inetnum" value="193.0.0.0 - 193.0.7.255"/>
+      </primary-key>
+      <attributes>
+        <attribute name="inetnum" value="193.0.0.0 - 193.0.7.255"/>
+        <attribute name="netname" value="RIPE-NCC"/>
+        <attribute name="country" value="NL"/>
+        <attribute name="org" value="ORG-RIEN1-RIPE" referenced-type="organisation"/>
+      </attributes>
+    </object>
+    <object type="inet6num">
+      <source id="ripe"/>
+      <attributes>
+        <attribute name="inet6num" value="2001:67c:2e8::/48"/>
+        <attribute name="netname" value="RIPE-NCC"/>
+        <attribute name="org" value="ORG-RIEN1-RIPE" referenced-type="organisation"/>
+      </attributes>
+    </object>
+    <object type="route">
+      <attributes>
+        <attribute name="route" value="193.0.0.0/21"/>
+        <attribute name="origin" value="AS3333"/>
+      </attributes>
+    </object>
+  </objects>
+</whois-resources>
+"#;
+
+    #[test]
+    fn parse_inetnum_and_inet6num() {
+        let result = RipeXmlParser::new().parse_content(RIPE_NCC.as_bytes()).unwrap();
+        let nets: Vec<(&str, String, String)> = result.ips
+            .iter()
+            .map(|ip| (ip.name.as_ref(), ip.start_ip.to_string(), ip.end_ip.to_string()))
+            .collect();
+        assert_eq!(nets,
+                   vec![("RIPE-NCC", String::from("193.0.0.0"), String::from("193.0.7.255")),
+                        ("RIPE-NCC",
+                         String::from("2001:67c:2e8::"),
+                         String::from("2001:67c:2e8:ffff:ffff:ffff:ffff:ffff"))]);
+        assert_eq!(result.ips[0].handle, None);
+        assert_eq!(result.ips[0].org, Some(String::from("ORG-RIEN1-RIPE")));
+        assert_eq!(result.ips[1].start_ip, IpAddr::from_str("2001:67c:2e8::").unwrap());
+    }
+
+    #[test]
+    fn parse_invalid_inetnum() {
+        let xml = r#"<whois-resources><objects><object type="inetnum"><attributes>
+<attribute name="inetnum" value="193.0.0.0"/>
+</attributes></object></objects></whois-resources>"#;
+        match RipeXmlParser::new().parse_content(xml.as_bytes()) {
+            Err(ParseError::XmlError(_)) => {}
+            other => panic!("Expected an invalid inetnum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_no_objects() {
+        let xml = r#"<whois-resources><objects></objects></whois-resources>"#;
+        assert!(RipeXmlParser::new().parse_content(xml.as_bytes()).unwrap().ips.is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use super::WhoisResult;
+use error::WhoisError;
+
+/// How many times a lookup may be handed on to another registry.
+pub const MAX_REFERRAL_DEPTH: usize = 3;
+
+/// Org handles ARIN uses for address space managed by the other RIRs.
+pub const REFERRAL_HANDLES: [&str; 4] = ["RIPE", "APNIC", "LACNIC", "AFRINIC"];
+
+/// Anything that can answer a lookup with a `WhoisResult`.
+pub trait WhoisSource {
+    /// Short lowercase name of the registry, e.g. `"arin"`.
+    fn name(&self) -> &str;
+
+    fn lookup(&self, handle: &str) -> Result<WhoisResult, WhoisError>;
+}
+
+/// Look `handle` up in `source`, re-querying whichever registry the result
+/// refers to until a result without a referral is found.
+///
+/// `registries` maps a referral handle (e.g. `"RIPE"`) to its source. A
+/// referral to a registry with no source, to a registry already asked, or
+/// more than `max_depth` referrals deep fails the lookup.
+///
+/// Each range is labelled with the name of the source that returned it.
+pub fn lookup_following_referrals(source: &dyn WhoisSource,
+                                  handle: &str,
+                                  registries: &[(&str, &dyn WhoisSource)],
+                                  max_depth: usize)
+                                  -> Result<WhoisResult, WhoisError> {
+    let mut visited: Vec<String> = Vec::new();
+    let mut answered_by = source;
+    let mut result = source.lookup(handle)?;
+    while let Some(referral) = result.referral.take() {
+        if visited.len() >= max_depth || visited.contains(&referral) {
+            return Err(WhoisError::TooManyReferrals(referral));
+        }
+        let referred = registries.iter().find(|&&(name, _)| name == referral);
+        match referred {
+            Some(&(_, referred_source)) => {
+                let mut fetches = result.fetches;
+                result = referred_source.lookup(handle)?;
+                fetches.append(&mut result.fetches);
+                result.fetches = fetches;
+                answered_by = referred_source;
+            }
+            None => return Err(WhoisError::UnsupportedReferral(referral)),
+        }
+        visited.push(referral);
+    }
+    for ip in &mut result.ips {
+        if ip.source.is_none() {
+            ip.source = Some(answered_by.name().to_string());
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::net::IpAddr;
+
+    use super::{WhoisSource, lookup_following_referrals, MAX_REFERRAL_DEPTH};
+    use super::super::{WhoisResult, WhoisIpResult};
+    use error::WhoisError;
+
+    struct MockSource {
+        name: &'static str,
+        referral: Option<&'static str>,
+        ips: Vec<(&'static str, &'static str)>,
+    }
+
+    impl WhoisSource for MockSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn lookup(&self, _handle: &str) -> Result<WhoisResult, WhoisError> {
+            let ips = self.ips
+                .iter()
+                .map(|&(start, end)| {
+                    WhoisIpResult::new(String::from("MOCK"),
+                                       IpAddr::from_str(start).unwrap(),
+                                       IpAddr::from_str(end).unwrap())
+                })
+                .collect();
+            let mut result = WhoisResult::new(ips);
+            result.referral = self.referral.map(String::from);
+            Ok(result)
+        }
+    }
+
+    #[test]
+    fn follows_ripe_referral() {
+        let arin = MockSource {
+            name: "arin",
+            referral: Some("RIPE"),
+            ips: vec![],
+        };
+        let ripe = MockSource {
+            name: "ripe",
+            referral: None,
+            ips: vec![("193.0.0.0", "193.0.7.255")],
+        };
+        let registries: Vec<(&str, &dyn WhoisSource)> = vec![("RIPE", &ripe)];
+        let result = lookup_following_referrals(&arin, "EXAMPLE", &registries, MAX_REFERRAL_DEPTH).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].start_ip, IpAddr::from_str("193.0.0.0").unwrap());
+        assert_eq!(result.ips[0].end_ip, IpAddr::from_str("193.0.7.255").unwrap());
+        assert_eq!(result.ips[0].source, Some(String::from("ripe")));
+        assert!(result.referral.is_none());
+    }
+
+    #[test]
+    fn no_referral() {
+        let arin = MockSource {
+            name: "arin",
+            referral: None,
+            ips: vec![("162.125.0.0", "162.125.255.255")],
+        };
+        let result = lookup_following_referrals(&arin, "DROPB", &[], MAX_REFERRAL_DEPTH).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].source, Some(String::from("arin")));
+    }
+
+    #[test]
+    fn unsupported_referral() {
+        let arin = MockSource {
+            name: "arin",
+            referral: Some("APNIC"),
+            ips: vec![],
+        };
+        match lookup_following_referrals(&arin, "EXAMPLE", &[], MAX_REFERRAL_DEPTH) {
+            Err(WhoisError::UnsupportedReferral(ref registry)) if registry == "APNIC" => {}
+            other => panic!("Expected an unsupported referral, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn referral_loop() {
+        let arin = MockSource {
+            name: "arin",
+            referral: Some("RIPE"),
+            ips: vec![],
+        };
+        let ripe = MockSource {
+            name: "ripe",
+            referral: Some("RIPE"),
+            ips: vec![],
+        };
+        let registries: Vec<(&str, &dyn WhoisSource)> = vec![("RIPE", &ripe)];
+        match lookup_following_referrals(&arin, "EXAMPLE", &registries, MAX_REFERRAL_DEPTH) {
+            Err(WhoisError::TooManyReferrals(_)) => {}
+            other => panic!("Expected a referral loop error, got {:?}", other),
+        }
+    }
+}
@@ -1,19 +1,53 @@
+use std::error;
 use std::net::IpAddr;
 use std::io;
+use std::mem;
 use std::str::FromStr;
 use std::fmt;
 
-use xml::reader::{EventReader, XmlEvent};
+use xml::common::Position;
+use xml::reader::{self, EventReader, XmlEvent};
 use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
 
 use super::{WhoisResult, WhoisIpResult};
+use jsonparser::JsonWhoisParser;
+use ripeparser::RipeXmlParser;
+use rdapparser::RdapJsonParser;
+use registry::Registry;
+use source::REFERRAL_HANDLES;
+
+/// The namespace of ARIN's core Whois-RWS elements. Others, such as the
+/// `ns2:` reverse DNS ones, can reuse the same local names.
+pub const ARIN_CORE_NAMESPACE: &str = "http://www.arin.net/whoisrws/core/v1";
+
+/// The local name of `name` if it is one of ARIN's core elements.
+///
+/// Attributes are matched on their local name alone, as unprefixed ones
+/// are in no namespace.
+fn core_name(name: &OwnedName) -> Option<&str> {
+    match name.namespace {
+        Option::Some(ref namespace) if namespace == ARIN_CORE_NAMESPACE => Option::Some(&name.local_name),
+        _ => Option::None,
+    }
+}
 
 #[derive(Debug)]
 #[derive(PartialEq)]
 pub enum ParseError {
     XmlError(String),
     IpAddrError(String),
-    LimitExceeded,
+    /// ARIN stopped at its result limit, the `limit` attribute if it gave one
+    LimitExceeded { limit: Option<String> },
+    Strict(ParseWarning),
+    /// A record of a bulk Whois file, see `BulkFileParser`, is malformed
+    BulkRecord(String),
+    /// A JSON response, see `JsonWhoisParser`, is malformed
+    JsonError(String),
+    /// The registry has no such record, e.g. RIPE has no points of contact list
+    Unsupported(String),
+    /// A net has no start or end address, with the net's handle if it has one
+    MissingAddress(Option<String>),
 }
 
 impl fmt::Display for ParseError {
@@ -21,28 +55,320 @@ impl fmt::Display for ParseError {
         match *self {
             ParseError::XmlError(ref expr) => write!(fmt, "{}", expr),
             ParseError::IpAddrError(ref expr) => write!(fmt, "{}", expr),
-            ParseError::LimitExceeded => write!(fmt, "API result limit exceeded"),
+            ParseError::LimitExceeded { limit: Some(ref limit) } => {
+                write!(fmt, "API result limit of {} exceeded", limit)
+            }
+            ParseError::LimitExceeded { limit: None } => write!(fmt, "API result limit exceeded"),
+            ParseError::Strict(ref warning) => write!(fmt, "{}", warning),
+            ParseError::BulkRecord(ref expr) => write!(fmt, "{}", expr),
+            ParseError::JsonError(ref expr) => write!(fmt, "{}", expr),
+            ParseError::Unsupported(ref expr) => write!(fmt, "{}", expr),
+            ParseError::MissingAddress(Some(ref handle)) => write!(fmt, "Net {} has no start or end address", handle),
+            ParseError::MissingAddress(None) => write!(fmt, "A net has no start or end address"),
         }
     }
 }
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ParseError::Strict(ref warning) => Some(warning),
+            _ => None,
+        }
+    }
+}
+
+impl From<reader::Error> for ParseError {
+    /// An `XmlError` giving where in the document it went wrong, counting
+    /// lines and columns from 1.
+    fn from(err: reader::Error) -> ParseError {
+        let position = err.position();
+        ParseError::XmlError(format!("{} at line {}, column {}",
+                                     err.msg(),
+                                     position.row + 1,
+                                     position.column + 1))
+    }
+}
+
+/// Inconsistencies in a response that do not stop it being parsed.
+///
+/// These are collected on the `WhoisResult`, or turned into a
+/// `ParseError::Strict` when the parser is in strict mode.
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum ParseWarning {
+    /// The `netRef` URL points at a different net than its `handle` attribute
+    HandleMismatch { handle: String, url: String },
+    /// ARIN stopped at its result limit, so some nets are missing
+    LimitExceeded { limit: Option<String> },
+    /// The net's `version` is not the IP version of its `startAddress`
+    VersionMismatch { version: u8, address: IpAddr },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseWarning::HandleMismatch { ref handle, ref url } => {
+                write!(fmt, "netRef handle {} does not match its URL {}", handle, url)
+            }
+            ParseWarning::LimitExceeded { limit: Some(ref limit) } => {
+                write!(fmt, "API result limit of {} exceeded, results are partial", limit)
+            }
+            ParseWarning::LimitExceeded { limit: None } => {
+                write!(fmt, "API result limit exceeded, results are partial")
+            }
+            ParseWarning::VersionMismatch { version, address } => {
+                write!(fmt, "net declares IP version {} but starts at {}", version, address)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseWarning {}
+
+/// What a net's own record (`/rest/net/<handle>`) adds to its `netRef`.
+#[derive(Debug, Default, PartialEq)]
+pub struct NetDetails {
+    /// The ASNs announcing the net, without the `AS` prefix
+    pub origin_ases: Vec<u32>,
+    /// The free text `comment` lines, in order
+    pub comments: Vec<String>,
+    /// Handle of the org the net is registered to
+    pub org: Option<String>,
+    /// ARIN's handle for the net, e.g. `NET-162-125-0-0-1`
+    pub handle: Option<String>,
+    pub name: Option<String>,
+    /// The net's own first address, not those of its `netBlocks`
+    pub start_ip: Option<IpAddr>,
+    /// The net's own last address
+    pub end_ip: Option<IpAddr>,
+    /// The `cidrLength` of the net's first `netBlock`
+    pub cidr_length: Option<u8>,
+    /// The `registrationDate`, as given
+    pub registration_date: Option<String>,
+    /// The `updateDate`, as given
+    pub update_date: Option<String>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// A point of contact linked to an org, from `/rest/org/<handle>/pocs`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PocLink {
+    /// ARIN's handle for the contact, e.g. `ABUSE2839-ARIN`
+    pub handle: String,
+    /// What the contact is for, e.g. `Abuse`, `Admin`, `Tech` or `NOC`
+    pub role: String,
+}
+
 pub trait WhoisXmlParser {
     fn parse_content<T: io::Read>(&self, xml: T) -> Result<WhoisResult, ParseError>;
+    fn parse_net_details<T: io::Read>(&self, xml: T) -> Result<NetDetails, ParseError>;
+    fn parse_pocs<T: io::Read>(&self, xml: T) -> Result<Vec<PocLink>, ParseError>;
+    /// The email addresses of a point of contact's own record,
+    /// `/rest/poc/<handle>`, in order.
+    fn parse_poc_emails<T: io::Read>(&self, xml: T) -> Result<Vec<String>, ParseError>;
+    /// The handle of the org an AS number's record, `/rest/asn/AS<number>`,
+    /// is registered to.
+    fn parse_asn_org<T: io::Read>(&self, xml: T) -> Result<String, ParseError>;
+
+    /// The media type to ask ARIN for in the Accept header.
+    fn accept(&self) -> &'static str {
+        "application/xml"
+    }
+}
+
+/// Any of the parsers, picked at runtime with `--api-format` and `--registry`.
+#[derive(Debug)]
+pub enum WhoisParser {
+    Xml(StdWhoisXmlParser),
+    Json(JsonWhoisParser),
+    Ripe(RipeXmlParser),
+    Rdap(RdapJsonParser),
+}
+
+impl WhoisParser {
+    /// The parser for the responses of a registry other than ARIN, whose
+    /// parser depends on `--api-format` instead.
+    pub fn for_registry(registry: Registry) -> WhoisParser {
+        match registry {
+            Registry::Arin => WhoisParser::Xml(StdWhoisXmlParser::new()),
+            Registry::Ripe => WhoisParser::Ripe(RipeXmlParser::new()),
+            Registry::Apnic | Registry::Lacnic | Registry::Afrinic => WhoisParser::Rdap(RdapJsonParser::new()),
+        }
+    }
+}
+
+impl WhoisXmlParser for WhoisParser {
+    fn parse_content<T: io::Read>(&self, content: T) -> Result<WhoisResult, ParseError> {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.parse_content(content),
+            WhoisParser::Json(ref parser) => parser.parse_content(content),
+            WhoisParser::Ripe(ref parser) => parser.parse_content(content),
+            WhoisParser::Rdap(ref parser) => parser.parse_content(content),
+        }
+    }
+
+    fn parse_net_details<T: io::Read>(&self, content: T) -> Result<NetDetails, ParseError> {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.parse_net_details(content),
+            WhoisParser::Json(ref parser) => parser.parse_net_details(content),
+            WhoisParser::Ripe(ref parser) => parser.parse_net_details(content),
+            WhoisParser::Rdap(ref parser) => parser.parse_net_details(content),
+        }
+    }
+
+    fn parse_pocs<T: io::Read>(&self, content: T) -> Result<Vec<PocLink>, ParseError> {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.parse_pocs(content),
+            WhoisParser::Json(ref parser) => parser.parse_pocs(content),
+            WhoisParser::Ripe(ref parser) => parser.parse_pocs(content),
+            WhoisParser::Rdap(ref parser) => parser.parse_pocs(content),
+        }
+    }
+
+    fn parse_poc_emails<T: io::Read>(&self, content: T) -> Result<Vec<String>, ParseError> {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.parse_poc_emails(content),
+            WhoisParser::Json(ref parser) => parser.parse_poc_emails(content),
+            WhoisParser::Ripe(ref parser) => parser.parse_poc_emails(content),
+            WhoisParser::Rdap(ref parser) => parser.parse_poc_emails(content),
+        }
+    }
+
+    fn parse_asn_org<T: io::Read>(&self, content: T) -> Result<String, ParseError> {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.parse_asn_org(content),
+            WhoisParser::Json(ref parser) => parser.parse_asn_org(content),
+            WhoisParser::Ripe(ref parser) => parser.parse_asn_org(content),
+            WhoisParser::Rdap(ref parser) => parser.parse_asn_org(content),
+        }
+    }
+
+    fn accept(&self) -> &'static str {
+        match *self {
+            WhoisParser::Xml(ref parser) => parser.accept(),
+            WhoisParser::Json(ref parser) => parser.accept(),
+            WhoisParser::Ripe(ref parser) => parser.accept(),
+            WhoisParser::Rdap(ref parser) => parser.accept(),
+        }
+    }
 }
 
 #[derive(Debug)]
-pub struct StdWhoisXmlParser {}
+pub struct StdWhoisXmlParser {
+    strict: bool,
+    allow_partial: bool,
+    multi_document: bool,
+}
 
 impl StdWhoisXmlParser {
     pub fn new() -> StdWhoisXmlParser {
-        StdWhoisXmlParser {}
+        StdWhoisXmlParser {
+            strict: false,
+            allow_partial: false,
+            multi_document: false,
+        }
+    }
+
+    /// In strict mode any `ParseWarning` fails the parse instead.
+    pub fn strict(mut self, strict: bool) -> StdWhoisXmlParser {
+        self.strict = strict;
+        self
     }
 
-    fn parse_ip(ip_str: &str) -> Result<IpAddr, ParseError> {
+    /// Return the nets ARIN did send when it hits its result limit, with a
+    /// `ParseWarning::LimitExceeded`, instead of `ParseError::LimitExceeded`.
+    pub fn allow_partial(mut self, allow_partial: bool) -> StdWhoisXmlParser {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    /// Accept several complete documents one after another, e.g. saved
+    /// pages stitched together, combining the nets of all of them.
+    pub fn multi_document(mut self, multi_document: bool) -> StdWhoisXmlParser {
+        self.multi_document = multi_document;
+        self
+    }
+
+    /// Parse the nets of an org's `nets` response one at a time as they are
+    /// read, rather than all at once like `parse_content`.
+    ///
+    /// A result limit ends the nets with `ParseError::LimitExceeded`, unless
+    /// `allow_partial` is set. Warnings and referrals are not reported, and
+    /// `strict` and `multi_document` do not apply.
+    pub fn nets<T: io::Read>(&self, xml: T) -> Nets<T> {
+        Nets {
+            reader: EventReader::new(xml),
+            allow_partial: self.allow_partial,
+            is_inside_limit: Option::None,
+            done: false,
+        }
+    }
+
+    fn warn(&self, warnings: &mut Vec<ParseWarning>, warning: ParseWarning) -> Result<(), ParseError> {
+        if self.strict {
+            return Err(ParseError::Strict(warning));
+        }
+        warnings.push(warning);
+        Ok(())
+    }
+
+    pub fn check_netref_url(handle: &str, url: &str) -> Option<ParseWarning> {
+        let url = url.trim();
+        match url.trim_end_matches('/').rsplit('/').next() {
+            Some(url_handle) if url_handle == handle => None,
+            _ => {
+                Some(ParseWarning::HandleMismatch {
+                         handle: handle.to_string(),
+                         url: url.to_string(),
+                     })
+            }
+        }
+    }
+
+    pub fn check_version(version: u8, address: IpAddr) -> Option<ParseWarning> {
+        let address_version = if address.is_ipv4() { 4 } else { 6 };
+        if version == address_version {
+            return None;
+        }
+        Some(ParseWarning::VersionMismatch {
+                 version: version,
+                 address: address,
+             })
+    }
+
+    /// Parse an origin AS, given as its number with or without an `AS`
+    /// prefix in any case, e.g. `19679`, `AS19679` or `as19679`.
+    pub fn parse_asn(asn_str: &str) -> Result<u32, ParseError> {
+        let asn_str = asn_str.trim();
+        let digits = match asn_str.get(..2) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("AS") => &asn_str[2..],
+            _ => asn_str,
+        };
+        digits.parse::<u32>().map_err(|e| ParseError::XmlError(format!("Failed to parse origin AS: {:} ({:})", e, asn_str)))
+    }
+
+    /// Parse an `--asn`, reporting a bad one as a mistake on the command
+    /// line rather than in a document ARIN sent.
+    pub fn parse_asn_arg(s: &str) -> Result<u32, String> {
+        StdWhoisXmlParser::parse_asn(s)
+            .map_err(|_| format!("Invalid --asn {:?}: expected an AS number, e.g. 19679 or AS19679", s))
+    }
+
+    /// Whether the text of a `limitExceeded` says the limit was hit. Only a
+    /// `true`, in any case, does.
+    pub fn is_limit_exceeded(text: &str) -> bool {
+        text.trim().eq_ignore_ascii_case("true")
+    }
+
+    pub fn parse_ip(ip_str: &str) -> Result<IpAddr, ParseError> {
+        let ip_str = ip_str.trim();
         IpAddr::from_str(ip_str).map_err(|e| ParseError::IpAddrError(format!("Failed to parse IP address: {:} ({:})", e, ip_str)))
     }
 
-    fn parse_content_netref(attributes: Vec<OwnedAttribute>) -> Result<WhoisIpResult, ParseError> {
+    fn parse_content_netref(attributes: Vec<OwnedAttribute>) -> Result<(WhoisIpResult, Option<String>), ParseError> {
         let mut range_name: Option<String> = Option::None;
+        let mut handle: Option<String> = Option::None;
         let mut start_ip: Option<IpAddr> = Option::None;
         let mut end_ip: Option<IpAddr> = Option::None;
 
@@ -51,6 +377,9 @@ impl StdWhoisXmlParser {
                 "name" => {
                     range_name = Option::Some(attribute.value.clone());
                 }
+                "handle" => {
+                    handle = Option::Some(attribute.value.clone());
+                }
                 "startAddress" => {
                     let ip = StdWhoisXmlParser::parse_ip(&attribute.value)?;
                     start_ip = Option::Some(ip);
@@ -63,69 +392,375 @@ impl StdWhoisXmlParser {
             }
         }
 
-        Ok(WhoisIpResult {
-               name: range_name.unwrap(),
-               start_ip: start_ip.unwrap(),
-               end_ip: end_ip.unwrap(),
-           })
+        let (start_ip, end_ip) = match (start_ip, end_ip) {
+            (Option::Some(start_ip), Option::Some(end_ip)) => (start_ip, end_ip),
+            _ => return Err(ParseError::MissingAddress(handle)),
+        };
+        let range_name = match range_name {
+            Option::Some(range_name) => range_name,
+            Option::None => return Err(ParseError::XmlError(String::from("netRef has no name"))),
+        };
+        let mut ip_result = WhoisIpResult::new(range_name, start_ip, end_ip);
+        ip_result.handle = handle.clone();
+        Ok((ip_result, handle))
     }
-}
 
-impl WhoisXmlParser for StdWhoisXmlParser {
-    fn parse_content<T: io::Read>(&self, xml: T) -> Result<WhoisResult, ParseError> {
+    /// Split `content` before every XML declaration after the first, dropping
+    /// parts that are only whitespace. Processing instructions such as
+    /// `<?xml-stylesheet` do not start a new document.
+    fn split_documents(content: &str) -> Vec<&str> {
+        let mut starts: Vec<usize> = content.match_indices("<?xml")
+            .map(|(i, _)| i)
+            .filter(|&i| i > 0 && content[i + 5..].starts_with(|c: char| c.is_whitespace() || c == '?'))
+            .collect();
+        starts.insert(0, 0);
+        starts.push(content.len());
+        starts.windows(2)
+            .map(|bounds| &content[bounds[0]..bounds[1]])
+            .filter(|document| !document.trim().is_empty())
+            .collect()
+    }
+
+    fn parse_document<T: io::Read>(&self, xml: T) -> Result<WhoisResult, ParseError> {
         let mut ip_results: Vec<WhoisIpResult> = Vec::new();
+        let mut warnings: Vec<ParseWarning> = Vec::new();
+        let mut referral: Option<String> = Option::None;
 
         let parser = EventReader::new(xml);
-        let mut is_inside_limit = false;
+        // The limit attribute and text so far of the limitExceeded element being parsed
+        let mut is_inside_limit: Option<(Option<String>, String)> = Option::None;
+        // The limit of the first limitExceeded element that was true
+        let mut limit_exceeded: Option<Option<String>> = Option::None;
+        // The netRef being parsed, its handle and the URL text seen so far
+        let mut netref: Option<(WhoisIpResult, Option<String>, String)> = Option::None;
         for elm in parser {
             match elm {
                 Ok(XmlEvent::StartElement { name, attributes, .. }) => {
-                    match name.local_name.as_ref() {
+                    match core_name(&name).unwrap_or_default() {
                         "netRef" => {
-                            let ip_result = StdWhoisXmlParser::parse_content_netref(attributes)?;
-                            ip_results.push(ip_result);
+                            let (ip_result, handle) = StdWhoisXmlParser::parse_content_netref(attributes)?;
+                            netref = Option::Some((ip_result, handle, String::new()));
                         }
                         "limitExceeded" => {
-                            is_inside_limit = true;
+                            let limit = attributes.into_iter().find(|a| a.name.local_name == "limit");
+                            is_inside_limit = Option::Some((limit.map(|a| a.value), String::new()));
+                        }
+                        "orgRef" => {
+                            let handle = attributes.iter().find(|a| a.name.local_name == "handle");
+                            if let Option::Some(handle) = handle {
+                                if REFERRAL_HANDLES.contains(&handle.value.as_ref()) {
+                                    referral = Option::Some(handle.value.clone());
+                                }
+                            }
                         }
                         _ => {}
                     }
                 }
-                Ok(XmlEvent::Characters(s)) => {
-                    if is_inside_limit {
-                        match s.as_ref() {
-                            "false" => {}
-                            _ => {
-                                return Err(ParseError::LimitExceeded);
+                Ok(XmlEvent::Characters(s)) |
+                Ok(XmlEvent::CData(s)) => {
+                    if let Option::Some((_, _, ref mut url)) = netref {
+                        url.push_str(&s);
+                    }
+                    if let Option::Some((_, ref mut text)) = is_inside_limit {
+                        text.push_str(&s);
+                    }
+                }
+                Ok(XmlEvent::EndElement { .. }) => {
+                    if let Option::Some((limit, text)) = is_inside_limit.take() {
+                        // A later false does not undo an earlier true
+                        if StdWhoisXmlParser::is_limit_exceeded(&text) && limit_exceeded.is_none() {
+                            limit_exceeded = Option::Some(limit);
+                        }
+                    }
+                    if let Option::Some((ip_result, handle, url)) = netref.take() {
+                        if let Option::Some(handle) = handle {
+                            if !url.trim().is_empty() {
+                                if let Option::Some(warning) = StdWhoisXmlParser::check_netref_url(&handle, &url) {
+                                    self.warn(&mut warnings, warning)?;
+                                }
                             }
                         }
+                        ip_results.push(ip_result);
+                    }
+                }
+                Err(e) => {
+                    return Err(ParseError::from(e));
+                }
+                _ => {}
+            }
+        }
+
+        if let Option::Some(limit) = limit_exceeded {
+            if !self.allow_partial {
+                return Err(ParseError::LimitExceeded { limit: limit });
+            }
+            warnings.push(ParseWarning::LimitExceeded { limit: limit });
+        }
+
+        let mut result = WhoisResult::new(ip_results);
+        result.warnings = warnings;
+        result.referral = referral;
+        Ok(result)
+    }
+}
+
+impl WhoisXmlParser for StdWhoisXmlParser {
+    fn parse_content<T: io::Read>(&self, mut xml: T) -> Result<WhoisResult, ParseError> {
+        if !self.multi_document {
+            return self.parse_document(xml);
+        }
+        let mut content = String::new();
+        xml.read_to_string(&mut content).map_err(|e| ParseError::XmlError(e.to_string()))?;
+        let mut result = WhoisResult::new(Vec::new());
+        for document in StdWhoisXmlParser::split_documents(&content) {
+            let mut parsed = self.parse_document(document.as_bytes())?;
+            result.ips.append(&mut parsed.ips);
+            result.warnings.append(&mut parsed.warnings);
+            result.referral = result.referral.or(parsed.referral);
+        }
+        Ok(result)
+    }
+
+    fn parse_net_details<T: io::Read>(&self, xml: T) -> Result<NetDetails, ParseError> {
+        let mut details = NetDetails::default();
+
+        let parser = EventReader::new(xml);
+        let mut current_element: Option<String> = Option::None;
+        // The text of the current element, which may come in several events
+        let mut text = String::new();
+        let mut is_inside_comment = false;
+        let mut is_inside_net_blocks = false;
+        let mut version: Option<u8> = Option::None;
+        for elm in parser {
+            match elm {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    match core_name(&name).unwrap_or_default() {
+                        "comment" => {
+                            is_inside_comment = true;
+                        }
+                        "netBlocks" => {
+                            is_inside_net_blocks = true;
+                        }
+                        "orgRef" => {
+                            let handle = attributes.into_iter().find(|a| a.name.local_name == "handle");
+                            details.org = handle.map(|a| a.value);
+                        }
+                        "netBlock" if details.cidr_length.is_none() => {
+                            let cidr_length = attributes.into_iter().find(|a| a.name.local_name == "cidrLength");
+                            details.cidr_length = cidr_length.and_then(|a| a.value.trim().parse::<u8>().ok());
+                        }
+                        _ => {}
+                    }
+                    current_element = core_name(&name).map(String::from);
+                    text.clear();
+                }
+                Ok(XmlEvent::Characters(s)) |
+                Ok(XmlEvent::CData(s)) => {
+                    text.push_str(&s);
+                }
+                Ok(XmlEvent::EndElement { name }) => {
+                    let s = mem::replace(&mut text, String::new());
+                    let element = current_element.take();
+                    match element.as_ref().map(|e| e.as_ref()) {
+                        _ if s.is_empty() => {}
+                        Option::Some("originAS") => {
+                            details.origin_ases.push(StdWhoisXmlParser::parse_asn(&s)?);
+                        }
+                        Option::Some("line") if is_inside_comment => {
+                            details.comments.push(s);
+                        }
+                        Option::Some("version") => {
+                            version = s.trim().parse::<u8>().ok();
+                        }
+                        Option::Some("cidrLength") if details.cidr_length.is_none() => {
+                            details.cidr_length = s.trim().parse::<u8>().ok();
+                        }
+                        // ARIN may list the netBlocks before the net's own
+                        // addresses, which their addresses must not stand in for
+                        _ if is_inside_net_blocks => {}
+                        Option::Some("startAddress") if details.start_ip.is_none() => {
+                            details.start_ip = Option::Some(StdWhoisXmlParser::parse_ip(&s)?);
+                        }
+                        Option::Some("endAddress") if details.end_ip.is_none() => {
+                            details.end_ip = Option::Some(StdWhoisXmlParser::parse_ip(&s)?);
+                        }
+                        Option::Some("registrationDate") if details.registration_date.is_none() => {
+                            details.registration_date = Option::Some(s.trim().to_string());
+                        }
+                        Option::Some("updateDate") if details.update_date.is_none() => {
+                            details.update_date = Option::Some(s.trim().to_string());
+                        }
+                        Option::Some("handle") if details.handle.is_none() => {
+                            details.handle = Option::Some(s.trim().to_string());
+                        }
+                        Option::Some("name") if details.name.is_none() => {
+                            details.name = Option::Some(s.trim().to_string());
+                        }
+                        _ => {}
+                    }
+                    match core_name(&name) {
+                        Option::Some("comment") => is_inside_comment = false,
+                        Option::Some("netBlocks") => is_inside_net_blocks = false,
+                        _ => {}
+                    }
+                }
+                Err(e) => {
+                    return Err(ParseError::from(e));
+                }
+                _ => {}
+            }
+        }
+        if let (Option::Some(version), Option::Some(address)) = (version, details.start_ip) {
+            if let Option::Some(warning) = StdWhoisXmlParser::check_version(version, address) {
+                self.warn(&mut details.warnings, warning)?;
+            }
+        }
+        Ok(details)
+    }
+
+    fn parse_pocs<T: io::Read>(&self, xml: T) -> Result<Vec<PocLink>, ParseError> {
+        let mut pocs = Vec::new();
+        for elm in EventReader::new(xml) {
+            match elm {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    if core_name(&name) != Option::Some("pocLinkRef") {
+                        continue;
+                    }
+                    let attribute = |name: &str| {
+                        attributes.iter().find(|a| a.name.local_name == name).map(|a| a.value.clone())
+                    };
+                    let handle = attribute("handle")
+                        .ok_or_else(|| ParseError::XmlError(String::from("pocLinkRef has no handle")))?;
+                    // The description is the readable form of the function code
+                    let role = attribute("description").or_else(|| attribute("function")).unwrap_or_default();
+                    pocs.push(PocLink {
+                        handle: handle,
+                        role: role,
+                    });
+                }
+                Err(e) => {
+                    return Err(ParseError::from(e));
+                }
+                _ => {}
+            }
+        }
+        Ok(pocs)
+    }
+
+    fn parse_poc_emails<T: io::Read>(&self, xml: T) -> Result<Vec<String>, ParseError> {
+        let mut emails = Vec::new();
+        let mut is_inside_email = false;
+        for elm in EventReader::new(xml) {
+            match elm {
+                Ok(XmlEvent::StartElement { name, .. }) => {
+                    is_inside_email = core_name(&name) == Option::Some("email");
+                }
+                Ok(XmlEvent::Characters(s)) |
+                Ok(XmlEvent::CData(s)) => {
+                    if is_inside_email {
+                        emails.push(s.trim().to_string());
                     }
                 }
                 Ok(XmlEvent::EndElement { .. }) => {
-                    is_inside_limit = false;
+                    is_inside_email = false;
                 }
-                Ok(XmlEvent::CData(_)) => {
-                    panic!("XML parser returned CData. This should never happen");
+                Err(e) => {
+                    return Err(ParseError::from(e));
+                }
+                _ => {}
+            }
+        }
+        Ok(emails)
+    }
+
+    fn parse_asn_org<T: io::Read>(&self, xml: T) -> Result<String, ParseError> {
+        for elm in EventReader::new(xml) {
+            match elm {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    if core_name(&name) == Option::Some("orgRef") {
+                        if let Option::Some(handle) = attributes.into_iter().find(|a| a.name.local_name == "handle") {
+                            return Ok(handle.value);
+                        }
+                    }
                 }
                 Err(e) => {
-                    return Err(ParseError::XmlError(e.to_string()));
+                    return Err(ParseError::from(e));
                 }
                 _ => {}
             }
         }
+        Err(ParseError::XmlError(String::from("The AS number has no orgRef")))
+    }
+}
+
+/// The nets of a `nets` response as they are parsed, see
+/// `StdWhoisXmlParser::nets`. A net that cannot be parsed gives an error in
+/// its place.
+pub struct Nets<R: io::Read> {
+    reader: EventReader<R>,
+    allow_partial: bool,
+    // The limit attribute and text so far of the limitExceeded element being parsed
+    is_inside_limit: Option<(Option<String>, String)>,
+    done: bool,
+}
+
+impl<R: io::Read> Iterator for Nets<R> {
+    type Item = Result<WhoisIpResult, ParseError>;
 
-        Ok(WhoisResult::new(ip_results))
+    fn next(&mut self) -> Option<Result<WhoisIpResult, ParseError>> {
+        while !self.done {
+            match self.reader.next() {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    match core_name(&name).unwrap_or_default() {
+                        "netRef" => {
+                            return Option::Some(StdWhoisXmlParser::parse_content_netref(attributes).map(|(ip, _)| ip));
+                        }
+                        "limitExceeded" => {
+                            let limit = attributes.into_iter().find(|a| a.name.local_name == "limit");
+                            self.is_inside_limit = Option::Some((limit.map(|a| a.value), String::new()));
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(XmlEvent::Characters(s)) |
+                Ok(XmlEvent::CData(s)) => {
+                    if let Option::Some((_, ref mut text)) = self.is_inside_limit {
+                        text.push_str(&s);
+                    }
+                }
+                Ok(XmlEvent::EndElement { .. }) => {
+                    if let Option::Some((limit, text)) = self.is_inside_limit.take() {
+                        if StdWhoisXmlParser::is_limit_exceeded(&text) && !self.allow_partial {
+                            self.done = true;
+                            return Option::Some(Err(ParseError::LimitExceeded { limit: limit }));
+                        }
+                    }
+                }
+                Ok(XmlEvent::EndDocument) => {
+                    self.done = true;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Option::Some(Err(ParseError::from(e)));
+                }
+                _ => {}
+            }
+        }
+        Option::None
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::error::Error;
     use std::str::FromStr;
     use std::net::IpAddr;
 
     use super::WhoisXmlParser;
     use super::StdWhoisXmlParser;
     use super::ParseError;
+    use super::ParseWarning;
+    use super::NetDetails;
 
     #[test]
     fn parse_content_empty() {
@@ -141,6 +776,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn parse_content_invalid_xml_position() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">
+</nets>
+"#.as_bytes();
+        match StdWhoisXmlParser::new().parse_content(xml) {
+            Err(ParseError::XmlError(message)) => assert!(message.contains("at line 4, column"), "{}", message),
+            other => panic!("Expected an XML error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn parse_content_empty_xml() {
         let xml = r#"<?xml version="1.0"?>
@@ -170,6 +818,7 @@ mod tests {
         assert_eq!(whois_result.ips.len(), 1);
         let whois_ip_result = whois_result.ips.get(0).unwrap();
         assert_eq!(whois_ip_result.name, String::from("DROPB"));
+        assert_eq!(whois_ip_result.handle, Some(String::from("NET-162-125-0-0-1")));
         assert_eq!(whois_ip_result.start_ip,
                    IpAddr::from_str("162.125.0.0").unwrap());
         assert_eq!(whois_ip_result.end_ip,
@@ -207,6 +856,19 @@ mod tests {
                    IpAddr::from_str("162.125.255.255").unwrap());
     }
 
+    #[test]
+    fn parse_content_utf8_name() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="192.0.2.255" startAddress="192.0.2.0" handle="NET-192-0-2-0-1" name="Société Générale">https://whois.arin.net/rest/net/NET-192-0-2-0-1</netRef>
+  <netRef endAddress="198.51.100.255" startAddress="198.51.100.0" handle="NET-198-51-100-0-1" name="&#x682a;&#x5f0f;&#x4f1a;&#x793e;">https://whois.arin.net/rest/net/NET-198-51-100-0-1</netRef>
+</nets>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_content(xml).unwrap();
+        assert_eq!(result.ips[0].name, "Société Générale");
+        assert_eq!(result.ips[1].name, "株式会社");
+    }
+
     #[test]
     fn parse_content_invalid_ip() {
         let xml = r#"<?xml version="1.0"?>
@@ -231,6 +893,560 @@ mod tests {
 "#.as_bytes();
         let result = StdWhoisXmlParser::new().parse_content(xml);
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParseError::LimitExceeded);
+        assert_eq!(result.unwrap_err(), ParseError::LimitExceeded { limit: Some(String::from("256")) });
+    }
+
+    /// A `nets` response with `count` nets, starting with a result limit of
+    /// `limit_exceeded`.
+    fn many_nets(count: usize, limit_exceeded: bool) -> String {
+        let mut xml = format!("<?xml version=\"1.0\"?>\n<nets xmlns=\"http://www.arin.net/whoisrws/core/v1\">\n  \
+                               <limitExceeded limit=\"256\">{}</limitExceeded>\n",
+                              limit_exceeded);
+        for i in 0..count {
+            xml.push_str(&format!("  <netRef endAddress=\"10.{0}.{1}.255\" startAddress=\"10.{0}.{1}.0\" \
+                                   handle=\"NET-10-{0}-{1}-0-1\" name=\"NET{2}\">\
+                                   https://whois.arin.net/rest/net/NET-10-{0}-{1}-0-1</netRef>\n",
+                                  i / 256,
+                                  i % 256,
+                                  i));
+        }
+        xml.push_str("</nets>\n");
+        xml
+    }
+
+    #[test]
+    fn nets_streamed() {
+        let xml = many_nets(2000, false);
+        let parser = StdWhoisXmlParser::new();
+        let mut nets = parser.nets(xml.as_bytes());
+        let first = nets.next().unwrap().unwrap();
+        assert_eq!(first.name, "NET0");
+        assert_eq!(first.handle, Some(String::from("NET-10-0-0-0-1")));
+        assert_eq!(nets.by_ref().take(9).count(), 9);
+        assert_eq!(nets.next().unwrap().unwrap().name, "NET10");
+        assert_eq!(nets.count(), 1989);
+    }
+
+    #[test]
+    fn nets_limit_exceeded() {
+        let xml = many_nets(3, true);
+        let errors: Vec<ParseError> = StdWhoisXmlParser::new()
+            .nets(xml.as_bytes())
+            .map(|net| net.unwrap_err())
+            .collect();
+        assert_eq!(errors, vec![ParseError::LimitExceeded { limit: Some(String::from("256")) }]);
+
+        let nets = StdWhoisXmlParser::new().allow_partial(true).nets(xml.as_bytes());
+        assert_eq!(nets.filter(|net| net.is_ok()).count(), 3);
+    }
+
+    #[test]
+    fn nets_invalid_xml() {
+        let mut nets = StdWhoisXmlParser::new().nets("<nets><netRef".as_bytes());
+        match nets.next() {
+            Some(Err(ParseError::XmlError(_))) => {}
+            other => panic!("Expected an XML error, got {:?}", other),
+        }
+        assert!(nets.next().is_none());
+    }
+
+    #[test]
+    fn parse_content_limit_exceeded_text() {
+        let nets = |limit_exceeded: &str| {
+            format!(r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">{}</limitExceeded>
+</nets>
+"#,
+                    limit_exceeded)
+        };
+        let parser = StdWhoisXmlParser::new();
+        assert!(parser.parse_content(nets("False").as_bytes()).is_ok());
+        assert!(parser.parse_content(nets(" false ").as_bytes()).is_ok());
+        assert!(parser.parse_content(nets("").as_bytes()).is_ok());
+        assert_eq!(parser.parse_content(nets(" true ").as_bytes()).unwrap_err(),
+                   ParseError::LimitExceeded { limit: Some(String::from("256")) });
+        assert!(parser.parse_content(nets("TRUE").as_bytes()).is_err());
+        // Text split over several events is read as a whole
+        assert!(parser.parse_content(nets("tr<![CDATA[ue]]>").as_bytes()).is_err());
+        assert!(parser.nets(nets("tr<![CDATA[ue]]>").as_bytes()).next().unwrap().is_err());
+        assert!(parser.nets(nets("False").as_bytes()).next().is_none());
+    }
+
+    #[test]
+    fn parse_content_limit_exceeded_duplicate() {
+        let false_then_true = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <limitExceeded limit="256">true</limitExceeded>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(false_then_true.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::LimitExceeded { limit: Some(String::from("256")) });
+
+        let true_then_false = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">true</limitExceeded>
+  <limitExceeded limit="256">false</limitExceeded>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(true_then_false.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::LimitExceeded { limit: Some(String::from("256")) });
+
+        let result = StdWhoisXmlParser::new().allow_partial(true).parse_content(true_then_false.as_bytes());
+        assert_eq!(result.unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn parse_content_limit_exceeded_allow_partial() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+  <limitExceeded limit="2">true</limitExceeded>
+  <netRef endAddress="199.47.219.255" startAddress="199.47.216.0" handle="NET-199-47-216-0-1" name="DROPBOX">https://whois.arin.net/rest/net/NET-199-47-216-0-1</netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::LimitExceeded { limit: Some(String::from("2")) });
+
+        let result = StdWhoisXmlParser::new().allow_partial(true).parse_content(xml.as_bytes());
+        assert!(result.is_ok());
+        let whois_result = result.unwrap();
+        assert_eq!(whois_result.ips.len(), 2);
+        assert_eq!(whois_result.ips[0].name, "DROPB");
+        assert_eq!(whois_result.ips[1].name, "DROPBOX");
+        assert_eq!(whois_result.warnings,
+                   vec![ParseWarning::LimitExceeded { limit: Some(String::from("2")) }]);
+        assert_eq!(whois_result.warnings[0].to_string(),
+                   "API result limit of 2 exceeded, results are partial");
+    }
+
+    #[test]
+    fn parse_content_multi_document() {
+        let xml = r#"<?xml version="1.0"?>
+<?xml-stylesheet type='text/xsl' href='http://whois.arin.net/xsl/website.xsl' ?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+</nets>
+<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <netRef endAddress="199.47.219.255" startAddress="199.47.216.0" handle="NET-199-47-216-0-1" name="DROPBOX">https://whois.arin.net/rest/net/NET-199-47-216-0-1</netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().multi_document(true).parse_content(xml.as_bytes());
+        assert!(result.is_ok());
+        let whois_result = result.unwrap();
+        let names: Vec<&str> = whois_result.ips.iter().map(|ip| ip.name.as_ref()).collect();
+        assert_eq!(names, vec!["DROPB", "DROPBOX"]);
+    }
+
+    #[test]
+    fn parse_content_netref_handle_mismatch() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-10-0-0-0-1</netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes());
+        assert!(result.is_ok());
+        let whois_result = result.unwrap();
+        assert_eq!(whois_result.ips.len(), 1);
+        let expected = ParseWarning::HandleMismatch {
+            handle: String::from("NET-162-125-0-0-1"),
+            url: String::from("https://whois.arin.net/rest/net/NET-10-0-0-0-1"),
+        };
+        assert_eq!(whois_result.warnings, vec![expected]);
+
+        let result = StdWhoisXmlParser::new().strict(true).parse_content(xml.as_bytes());
+        match result {
+            Err(ParseError::Strict(ParseWarning::HandleMismatch { .. })) => {}
+            other => panic!("Expected a strict handle mismatch error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_content_netref_handle_match() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256">false</limitExceeded>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().strict(true).parse_content(xml.as_bytes());
+        assert!(result.is_ok());
+        assert!(result.unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_content_referral() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <handle>NET-193-0-0-0-0</handle>
+  <name>RIPE-CBLK</name>
+  <orgRef handle="RIPE" name="RIPE Network Coordination Centre">https://whois.arin.net/rest/org/RIPE</orgRef>
+</net>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_content(xml);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().referral, Some(String::from("RIPE")));
+    }
+
+    #[test]
+    fn parse_content_no_referral() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <orgRef handle="DROPB" name="Dropbox, Inc.">https://whois.arin.net/rest/org/DROPB</orgRef>
+</net>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_content(xml);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().referral, None);
+    }
+
+    #[test]
+    fn parse_net_details_origin_ases() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <handle>NET-162-125-0-0-1</handle>
+  <name>DROPB</name>
+  <originASes>
+    <originAS>AS19679</originAS>
+    <originAS>AS54372</originAS>
+  </originASes>
+  <startAddress>162.125.0.0</startAddress>
+  <endAddress>162.125.255.255</endAddress>
+</net>
+"#.as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.origin_ases, vec![19679, 54372]);
+        assert_eq!(details.handle, Some(String::from("NET-162-125-0-0-1")));
+        assert_eq!(details.name, Some(String::from("DROPB")));
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+        assert_eq!(details.end_ip, Some(IpAddr::from_str("162.125.255.255").unwrap()));
+    }
+
+    #[test]
+    fn parse_error_is_error() {
+        let err: Box<dyn Error> = Box::new(ParseError::XmlError(String::from("unexpected end of stream")));
+        assert_eq!(err.to_string(), "unexpected end of stream");
+        assert!(err.source().is_none());
+
+        let warning = ParseWarning::LimitExceeded { limit: Some(String::from("256")) };
+        let err: Box<dyn Error> = Box::new(ParseError::Strict(warning));
+        assert_eq!(err.source().unwrap().to_string(), "API result limit of 256 exceeded, results are partial");
+    }
+
+    #[test]
+    fn parse_content_missing_address() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB">https://whois.arin.net/rest/net/NET-162-125-0-0-1</netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::MissingAddress(Some(String::from("NET-162-125-0-0-1"))));
+
+        let xml = r#"<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef endAddress="162.125.255.255" name="DROPB"/>
+</nets>"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::MissingAddress(None));
+    }
+
+    #[test]
+    fn parse_content_missing_name() {
+        let xml = r#"<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netRef startAddress="162.125.0.0" endAddress="162.125.255.255" handle="NET-162-125-0-0-1"/>
+</nets>"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes());
+        assert_eq!(result.unwrap_err(), ParseError::XmlError(String::from("netRef has no name")));
+    }
+
+    #[test]
+    fn parse_cdata() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <handle>NET-162-125-0-0-1</handle>
+  <startAddress><![CDATA[162.125.0.0]]></startAddress>
+  <endAddress>162.125.255.255</endAddress>
+</net>
+"#;
+        let details = StdWhoisXmlParser::new().parse_net_details(xml.as_bytes()).unwrap();
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1">
+  <limitExceeded limit="256"><![CDATA[false]]></limitExceeded>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB"><![CDATA[https://whois.arin.net/rest/net/NET-10-0-0-0-1]]></netRef>
+</nets>
+"#;
+        let result = StdWhoisXmlParser::new().parse_content(xml.as_bytes()).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        // The URL in the CDATA is still checked against the handle
+        assert_eq!(result.warnings.len(), 1);
+    }
+
+    #[test]
+    fn parse_net_details_org() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <orgRef handle="DROPB" name="Dropbox, Inc.">https://whois.arin.net/rest/org/DROPB</orgRef>
+</net>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_net_details(xml);
+        assert_eq!(result.unwrap().org, Some(String::from("DROPB")));
+    }
+
+    #[test]
+    fn parse_net_details_pretty_printed() {
+        let xml = "<?xml version=\"1.0\"?>
+<net xmlns=\"http://www.arin.net/whoisrws/core/v1\">
+  <startAddress>
+    162.125.0.0
+  </startAddress>
+  <endAddress>162.125.<![CDATA[255.255]]></endAddress>
+</net>
+".as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+        // Text split over several events is parsed as a whole
+        assert_eq!(details.end_ip, Some(IpAddr::from_str("162.125.255.255").unwrap()));
+    }
+
+    #[test]
+    fn parse_ip_whitespace() {
+        assert_eq!(StdWhoisXmlParser::parse_ip("\n  162.125.0.0\n"), Ok(IpAddr::from_str("162.125.0.0").unwrap()));
+        assert!(StdWhoisXmlParser::parse_ip("162.125. 0.0").is_err());
+    }
+
+    #[test]
+    fn parse_net_details_dates() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <registrationDate>2013-06-26T14:26:06-04:00</registrationDate>
+  <handle>NET-162-125-0-0-1</handle>
+  <updateDate>2021-12-14T17:47:27-05:00</updateDate>
+</net>
+"#.as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.registration_date, Some(String::from("2013-06-26T14:26:06-04:00")));
+        assert_eq!(details.update_date, Some(String::from("2021-12-14T17:47:27-05:00")));
+    }
+
+    #[test]
+    fn parse_net_details_cidr_length() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <netBlocks>
+    <netBlock cidrLength="16" startAddress="162.125.0.0" endAddress="162.125.255.255"/>
+    <netBlock cidrLength="24" startAddress="162.126.0.0" endAddress="162.126.0.255"/>
+  </netBlocks>
+</net>
+"#.as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.cidr_length, Some(16));
+    }
+
+    #[test]
+    fn parse_net_details_net_blocks_first() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <endAddress>162.125.255.255</endAddress>
+  <handle>NET-162-125-0-0-1</handle>
+  <netBlocks>
+    <netBlock>
+      <cidrLength>17</cidrLength>
+      <endAddress>162.125.255.255</endAddress>
+      <startAddress>162.125.128.0</startAddress>
+    </netBlock>
+    <netBlock>
+      <cidrLength>17</cidrLength>
+      <endAddress>162.125.127.255</endAddress>
+      <startAddress>162.125.0.0</startAddress>
+    </netBlock>
+  </netBlocks>
+  <startAddress>162.125.0.0</startAddress>
+</net>
+"#.as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+        assert_eq!(details.end_ip, Some(IpAddr::from_str("162.125.255.255").unwrap()));
+        assert_eq!(details.cidr_length, Some(17));
+        assert_eq!(details.handle, Some(String::from("NET-162-125-0-0-1")));
+    }
+
+    #[test]
+    fn parse_net_details_other_namespace() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1" xmlns:ns2="http://www.arin.net/whoisrws/rdns/v1">
+  <ns2:delegation>
+    <ns2:startAddress>10.0.0.0</ns2:startAddress>
+    <ns2:endAddress>10.0.0.255</ns2:endAddress>
+    <ns2:name>0.0.10.in-addr.arpa.</ns2:name>
+  </ns2:delegation>
+  <handle>NET-162-125-0-0-1</handle>
+  <name>DROPB</name>
+  <startAddress>162.125.0.0</startAddress>
+  <endAddress>162.125.255.255</endAddress>
+</net>
+"#.as_bytes();
+        let details = StdWhoisXmlParser::new().parse_net_details(xml).unwrap();
+        assert_eq!(details.start_ip, Some(IpAddr::from_str("162.125.0.0").unwrap()));
+        assert_eq!(details.end_ip, Some(IpAddr::from_str("162.125.255.255").unwrap()));
+        assert_eq!(details.name, Some(String::from("DROPB")));
+    }
+
+    #[test]
+    fn parse_content_other_namespace() {
+        let xml = r#"<?xml version="1.0"?>
+<nets xmlns="http://www.arin.net/whoisrws/core/v1" xmlns:ns2="http://www.arin.net/whoisrws/rdns/v1">
+  <ns2:netRef endAddress="10.0.0.255" startAddress="10.0.0.0" handle="NET-10-0-0-0-1" name="OTHER"/>
+  <netRef endAddress="162.125.255.255" startAddress="162.125.0.0" handle="NET-162-125-0-0-1" name="DROPB"/>
+</nets>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_content(xml).unwrap();
+        assert_eq!(result.ips.len(), 1);
+        assert_eq!(result.ips[0].name, "DROPB");
+    }
+
+    #[test]
+    fn parse_pocs() {
+        let xml = r#"<?xml version="1.0"?>
+<?xml-stylesheet type='text/xsl' href='https://whois.arin.net/xsl/website.xsl' ?>
+<pocs xmlns="http://www.arin.net/whoisrws/core/v1" termsOfUse="https://www.arin.net/resources/registry/whois/tou/">
+  <limitExceeded limit="256">false</limitExceeded>
+  <pocLinkRef description="Abuse" function="AB" handle="NETWO7047-ARIN">https://whois.arin.net/rest/poc/NETWO7047-ARIN</pocLinkRef>
+  <pocLinkRef description="Admin" function="AD" handle="DROPB1-ARIN">https://whois.arin.net/rest/poc/DROPB1-ARIN</pocLinkRef>
+  <pocLinkRef description="Tech" function="T" handle="NETWO7047-ARIN">https://whois.arin.net/rest/poc/NETWO7047-ARIN</pocLinkRef>
+</pocs>
+"#.as_bytes();
+        let pocs: Vec<(String, String)> = StdWhoisXmlParser::new()
+            .parse_pocs(xml)
+            .unwrap()
+            .into_iter()
+            .map(|poc| (poc.handle, poc.role))
+            .collect();
+        assert_eq!(pocs,
+                   vec![(String::from("NETWO7047-ARIN"), String::from("Abuse")),
+                        (String::from("DROPB1-ARIN"), String::from("Admin")),
+                        (String::from("NETWO7047-ARIN"), String::from("Tech"))]);
+
+        let xml = r#"<pocs xmlns="http://www.arin.net/whoisrws/core/v1"><pocLinkRef function="AB"/></pocs>"#;
+        assert!(StdWhoisXmlParser::new().parse_pocs(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parse_poc_emails() {
+        let xml = r#"<?xml version="1.0"?>
+<poc xmlns="http://www.arin.net/whoisrws/core/v1">
+  <emails>
+    <email>abuse@dropbox.com</email>
+    <email>noc@dropbox.com</email>
+  </emails>
+  <handle>NETWO7047-ARIN</handle>
+  <lastName>Network Abuse</lastName>
+</poc>
+"#.as_bytes();
+        assert_eq!(StdWhoisXmlParser::new().parse_poc_emails(xml).unwrap(),
+                   vec![String::from("abuse@dropbox.com"), String::from("noc@dropbox.com")]);
+    }
+
+    #[test]
+    fn parse_asn_org() {
+        let xml = r#"<?xml version="1.0"?>
+<asn xmlns="http://www.arin.net/whoisrws/core/v1">
+  <registrationDate>2010-11-15T16:13:18-05:00</registrationDate>
+  <ref>https://whois.arin.net/rest/asn/AS19679</ref>
+  <endAsNumber>19679</endAsNumber>
+  <handle>AS19679</handle>
+  <name>DROPBOX</name>
+  <orgRef handle="DROPB" name="Dropbox, Inc.">https://whois.arin.net/rest/org/DROPB</orgRef>
+  <startAsNumber>19679</startAsNumber>
+</asn>
+"#.as_bytes();
+        assert_eq!(StdWhoisXmlParser::new().parse_asn_org(xml), Ok(String::from("DROPB")));
+
+        let xml = r#"<asn xmlns="http://www.arin.net/whoisrws/core/v1"><handle>AS19679</handle></asn>"#;
+        assert!(StdWhoisXmlParser::new().parse_asn_org(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parse_net_details_comments() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <comment>
+    <line number="0">Abuse reports: abuse@example.com</line>
+    <line number="1">Used for the example.com CDN</line>
+  </comment>
+  <handle>NET-192-0-2-0-1</handle>
+</net>
+"#.as_bytes();
+        let result = StdWhoisXmlParser::new().parse_net_details(xml);
+        assert_eq!(result,
+                   Ok(NetDetails {
+                          origin_ases: vec![],
+                          comments: vec![String::from("Abuse reports: abuse@example.com"),
+                                         String::from("Used for the example.com CDN")],
+                          org: None,
+                          handle: Some(String::from("NET-192-0-2-0-1")),
+                          name: None,
+                          start_ip: None,
+                          end_ip: None,
+                          cidr_length: None,
+                          registration_date: None,
+                          update_date: None,
+                          warnings: vec![],
+                      }));
+    }
+
+    #[test]
+    fn parse_net_details_version_mismatch() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <handle>NET-162-125-0-0-1</handle>
+  <startAddress>162.125.0.0</startAddress>
+  <endAddress>162.125.255.255</endAddress>
+  <version>6</version>
+</net>
+"#;
+        let result = StdWhoisXmlParser::new().parse_net_details(xml.as_bytes());
+        assert_eq!(result.unwrap().warnings,
+                   vec![ParseWarning::VersionMismatch {
+                            version: 6,
+                            address: IpAddr::from_str("162.125.0.0").unwrap(),
+                        }]);
+
+        let result = StdWhoisXmlParser::new().strict(true).parse_net_details(xml.as_bytes());
+        assert!(result.is_err());
+
+        let matching = xml.replace("<version>6</version>", "<version>4</version>");
+        let result = StdWhoisXmlParser::new().strict(true).parse_net_details(matching.as_bytes());
+        assert_eq!(result.unwrap().warnings, vec![]);
+    }
+
+    #[test]
+    fn parse_asn() {
+        assert_eq!(StdWhoisXmlParser::parse_asn("19679"), Ok(19679));
+        assert_eq!(StdWhoisXmlParser::parse_asn(" AS19679\n"), Ok(19679));
+        assert_eq!(StdWhoisXmlParser::parse_asn("as19679"), Ok(19679));
+        assert_eq!(StdWhoisXmlParser::parse_asn("As19679"), Ok(19679));
+        assert!(StdWhoisXmlParser::parse_asn("ASX").is_err());
+        assert!(StdWhoisXmlParser::parse_asn("é").is_err());
+        assert_eq!(StdWhoisXmlParser::parse_asn_arg("as19679"), Ok(19679));
+        assert_eq!(StdWhoisXmlParser::parse_asn_arg("ASX"),
+                   Err(String::from("Invalid --asn \"ASX\": expected an AS number, e.g. 19679 or AS19679")));
+    }
+
+    #[test]
+    fn parse_net_details_invalid_asn() {
+        let xml = r#"<?xml version="1.0"?>
+<net xmlns="http://www.arin.net/whoisrws/core/v1">
+  <originASes><originAS>ASX</originAS></originASes>
+</net>
+"#.as_bytes();
+        assert!(StdWhoisXmlParser::new().parse_net_details(xml).is_err());
     }
 }